@@ -0,0 +1,238 @@
+//! Headless command line mode for scripting, driving the same PipeWire thread as the GUI.
+
+use crate::pipewire;
+use crate::state::{AppState, AudioNode, PwCommand};
+use crossbeam_channel::unbounded;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage:
+  copper                            Open the mixer window
+  copper --list                     List devices and streams
+  copper --set-volume <node> <pct>  Set a node's volume in percent
+  copper --toggle-mute <node>       Mute or unmute a node
+  copper --set-default <node>       Make a device the default
+
+Any of these can be preceded by --remote <name> to use a PipeWire instance other than
+the default one; PIPEWIRE_REMOTE does the same.
+
+<node> is a numeric id, a name as shown by --list, or part of one.";
+
+/// How long to wait for the first full snapshot of the graph.
+const SYNC_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long PipeWire gets to report a change back before we give up on it.
+const APPLY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Highest volume accepted, matching the GUI with over-amplification allowed.
+const MAX_PERCENT: f32 = 150.0;
+
+enum Command {
+    Help,
+    List,
+    /// Change the node matching the query.
+    Apply(String, Action),
+}
+
+enum Action {
+    SetVolume(f32),
+    ToggleMute,
+    SetDefault,
+}
+
+fn parse(args: &[String]) -> Result<Command, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["--help" | "-h"] => Ok(Command::Help),
+        ["--list"] => Ok(Command::List),
+        ["--set-volume", node, percent] => Ok(Command::Apply(node.to_string(), Action::SetVolume(parse_percent(percent)?))),
+        ["--toggle-mute", node] => Ok(Command::Apply(node.to_string(), Action::ToggleMute)),
+        ["--set-default", node] => Ok(Command::Apply(node.to_string(), Action::SetDefault)),
+        _ => Err(format!("unrecognized arguments: {}", args.join(" "))),
+    }
+}
+
+/// Take `--remote <name>` out of `args`, wherever it is, and return the name.
+pub fn take_remote(args: &mut Vec<String>) -> Result<Option<String>, String> {
+    let Some(at) = args.iter().position(|a| a == "--remote") else { return Ok(None) };
+    if at + 1 >= args.len() {
+        return Err(format!("--remote needs a name\n\n{}", USAGE));
+    }
+    let name = args.remove(at + 1);
+    args.remove(at);
+    Ok(Some(name))
+}
+
+/// Parse a volume in percent, with or without a `%` sign.
+pub fn parse_percent(text: &str) -> Result<f32, String> {
+    let percent: f32 = text
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid volume \"{}\"", text))?;
+    if !percent.is_finite() || !(0.0..=MAX_PERCENT).contains(&percent) {
+        return Err(format!("volume must be between 0 and {}", MAX_PERCENT));
+    }
+    Ok(percent)
+}
+
+/// Run the command line in `args` and return the process exit code.
+pub fn run(args: &[String], app_state: AppState) -> i32 {
+    let command = match parse(args) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("copper: {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    let state = Arc::new(Mutex::new(app_state));
+    let (tx, rx) = unbounded::<PwCommand>();
+    let pipewire_thread = {
+        let state = state.clone();
+        std::thread::spawn(move || pipewire::run(state, rx, Arc::new(Mutex::new(None)), pipewire::Mode::Cli))
+    };
+
+    let code = if !wait_for(&state, SYNC_TIMEOUT, |s| s.synced) {
+        eprintln!("copper: could not connect to PipeWire");
+        1
+    } else {
+        match command {
+            Command::Apply(query, action) => apply(&query, action, &state, |cmd| {
+                let _ = tx.send(cmd);
+            }),
+            Command::List | Command::Help => {
+                print!("{}", format_list(&state.lock()));
+                0
+            }
+        }
+    };
+
+    let _ = tx.send(PwCommand::Quit);
+    let _ = pipewire_thread.join();
+    code
+}
+
+/// Apply `action` to the node matching `query` and wait for PipeWire to report it back.
+fn apply(query: &str, action: Action, state: &Arc<Mutex<AppState>>, send: impl Fn(PwCommand)) -> i32 {
+    let (node_id, name, muted, is_stream, current) = match find_node(&state.lock(), query) {
+        Ok(node) => (node.id, node.name.clone(), node.muted, node.is_stream, node.volume),
+        Err(e) => {
+            eprintln!("copper: {}", e);
+            return 1;
+        }
+    };
+
+    let (applied, message) = match action {
+        Action::SetVolume(percent) => {
+            let volume = percent / 100.0;
+            // PipeWire may not report back a volume that didn't change, so there is nothing to wait for.
+            if (current - volume).abs() < 0.005 {
+                println!("{} is already at {:.0}%", name, percent);
+                return 0;
+            }
+            send(PwCommand::SetVolume(node_id, volume));
+            let applied = wait_for(state, APPLY_TIMEOUT, |s| {
+                // The new volume shows up locally right away; an empty `sent_volumes` means PipeWire echoed it.
+                s.nodes.get(&node_id).is_some_and(|n| (n.volume - volume).abs() < 0.005 && n.sent_volumes.is_empty())
+            });
+            (applied, format!("Set {} to {:.0}%", name, percent))
+        }
+        Action::ToggleMute => {
+            send(PwCommand::SetMute(node_id, !muted));
+            let applied = wait_for(state, APPLY_TIMEOUT, |s| s.nodes.get(&node_id).is_some_and(|n| n.muted != muted));
+            (applied, format!("{} {}", if muted { "Unmuted" } else { "Muted" }, name))
+        }
+        Action::SetDefault => {
+            if is_stream {
+                eprintln!("copper: {} is a stream; only devices can be the default", name);
+                return 1;
+            }
+            send(PwCommand::SetDefault(node_id));
+            let applied = wait_for(state, APPLY_TIMEOUT, |s| {
+                s.default_sink_name.as_ref() == Some(&name) || s.default_source_name.as_ref() == Some(&name)
+            });
+            (applied, format!("Made {} the default", name))
+        }
+    };
+
+    if applied {
+        println!("{}", message);
+        0
+    } else {
+        eprintln!("copper: PipeWire did not confirm the change to {}", name);
+        1
+    }
+}
+
+/// One line per node: default marker, id, kind, volume, mute, stable id and description.
+pub fn format_list(state: &AppState) -> String {
+    let mut out = String::new();
+    let mut nodes: Vec<&AudioNode> = state.nodes.values().collect();
+    nodes.sort_by_key(|n| (n.is_stream, !n.is_sink, n.id));
+
+    for node in nodes {
+        let kind = match (node.is_stream, node.is_sink) {
+            (false, true) => "output",
+            (false, false) => "input",
+            (true, true) => "playback",
+            (true, false) => "recording",
+        };
+        let is_default = !node.is_stream
+            && [&state.default_sink_name, &state.default_source_name]
+                .iter()
+                .any(|name| name.as_ref() == Some(&node.name));
+
+        out += &format!(
+            "{} {:>5}  {:<9}  {:>4.0}%{}  {}  ({})\n",
+            if is_default { "*" } else { " " },
+            node.id,
+            kind,
+            node.volume * 100.0,
+            if node.muted { " muted" } else { "      " },
+            node.stable_id,
+            node.description
+        );
+    }
+    out
+}
+
+/// Find a node by numeric id, exact stable id or `node.name`, or a name substring matching
+/// exactly one node.
+pub fn find_node<'a>(state: &'a AppState, query: &str) -> Result<&'a AudioNode, String> {
+    if let Ok(id) = query.parse::<u32>() {
+        return state.nodes.get(&id).ok_or_else(|| format!("no node with id {}", id));
+    }
+    if let Some(node) = state.find_node_by_stable_id(query).or_else(|| state.find_node_by_name(query)) {
+        return Ok(node);
+    }
+
+    let query_lower = query.to_lowercase();
+    let mut matches: Vec<&AudioNode> =
+        state.nodes.values().filter(|n| n.stable_id.to_lowercase().contains(&query_lower)).collect();
+    match matches.len() {
+        0 => Err(format!("no node matches \"{}\"", query)),
+        1 => Ok(matches[0]),
+        _ => {
+            matches.sort_by_key(|n| n.id);
+            let names: Vec<String> = matches.iter().map(|n| format!("  {} {}", n.id, n.stable_id)).collect();
+            Err(format!("\"{}\" matches several nodes:\n{}", query, names.join("\n")))
+        }
+    }
+}
+
+/// Poll the shared state until `done` holds, for at most `timeout`.
+fn wait_for(state: &Arc<Mutex<AppState>>, timeout: Duration, done: impl Fn(&AppState) -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if done(&state.lock()) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
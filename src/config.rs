@@ -0,0 +1,202 @@
+use crate::state::{Settings, VolumeCurve};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// User configuration, read from `$XDG_CONFIG_HOME/copper/config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Expert option: force the channel count used when building volume PODs, keyed by
+    /// node name. A workaround for devices that misreport their channels.
+    pub channel_overrides: HashMap<String, u32>,
+    /// Initial volume in percent for new streams, keyed by `media.role` (e.g. `event = 50`).
+    pub role_volumes: HashMap<String, f32>,
+    /// Which tab the window opens on.
+    pub default_tab: DefaultTab,
+    /// How the volume sliders map to PipeWire's linear gain.
+    pub volume_curve: VolumeCurve,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultTab {
+    /// Reopen the tab that was last used.
+    #[default]
+    Remember,
+    Outputs,
+    Inputs,
+    Playback,
+    Recording,
+    Configuration,
+}
+
+/// Last volume (slider units) and mute of each application's playback streams,
+/// keyed by `application.name`.
+pub type AppVolumes = HashMap<String, (f32, bool)>;
+
+/// A named snapshot of volume (slider units) and mute, keyed by node stable id.
+pub type Preset = HashMap<String, (f32, bool)>;
+pub type Presets = BTreeMap<String, Preset>;
+
+/// Groups of devices, by stable id, whose volumes move together.
+pub type VolumeGroups = Vec<Vec<String>>;
+
+/// `volume-groups.toml`; TOML needs a table at the top, so the list sits under `groups`.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct VolumeGroupsFile {
+    groups: VolumeGroups,
+}
+
+/// What Copper remembers between launches, kept in `window.toml` next to the config.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Name of the tab that was open, e.g. `"Playback"`.
+    pub tab: Option<String>,
+    /// The accent color picked in the settings, as RGB.
+    pub accent_color: Option<[u8; 3]>,
+    pub theme: Theme,
+    pub window: Option<WindowGeometry>,
+    /// Keep the window above all others.
+    pub always_on_top: bool,
+    /// Hide the window's title bar and borders.
+    pub borderless: bool,
+    /// The toggles from the settings, such as volume meters and the compact layout.
+    pub settings: Settings,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Dark,
+    Light,
+    /// Follow the desktop's light/dark preference.
+    #[default]
+    System,
+}
+
+/// The last window size and position.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    /// Unknown on Wayland, which doesn't expose window positions.
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+}
+
+fn config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("copper"))
+}
+
+/// Load the config file, falling back to defaults if it is missing or invalid.
+pub fn load() -> Config {
+    load_toml("config.toml", "config")
+}
+
+/// Read `file` from the config dir, falling back to the default if it is missing or invalid.
+/// `what` names the contents in the warning.
+fn load_toml<T: DeserializeOwned + Default>(file: &str, what: &str) -> T {
+    let Some(path) = config_dir().map(|dir| dir.join(file)) else { return T::default() };
+    let Ok(text) = std::fs::read_to_string(&path) else { return T::default() };
+
+    match toml::from_str(&text) {
+        Ok(value) => value,
+        Err(e) => {
+            log::warn!("Ignoring invalid {} {}: {}", what, path.display(), e);
+            T::default()
+        }
+    }
+}
+
+/// Write `value` to `file` in the config dir, creating the dir if needed.
+fn save_toml<T: Serialize>(file: &str, what: &str, value: &T) {
+    let Some(dir) = config_dir() else { return };
+    let path = dir.join(file);
+
+    let result = toml::to_string(value)
+        .map_err(|e| e.to_string())
+        .and_then(|text| {
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            std::fs::write(&path, text).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log::warn!("Failed to save {} to {}: {}", what, path.display(), e);
+    }
+}
+
+/// Load what was saved at the end of the last run, dropping anything unusable.
+pub fn load_session() -> Session {
+    let mut session: Session = load_toml("window.toml", "saved state");
+    session.window = session
+        .window
+        .filter(|g| g.width > 0.0 && g.height > 0.0 && g.width.is_finite() && g.height.is_finite());
+    let step = session.settings.tray_scroll_step;
+    session.settings.tray_scroll_step = if step.is_finite() {
+        step.clamp(0.01, 0.2)
+    } else {
+        Settings::default().tray_scroll_step
+    };
+    session
+}
+
+pub fn save_session(session: &Session) {
+    save_toml("window.toml", "state", session);
+}
+
+/// Load the saved per-application volumes from `app-volumes.toml`.
+pub fn load_app_volumes() -> AppVolumes {
+    load_toml("app-volumes.toml", "app volumes")
+}
+
+pub fn save_app_volumes(volumes: &AppVolumes) {
+    save_toml("app-volumes.toml", "app volumes", volumes);
+}
+
+/// Load the saved volume presets from `presets.toml`.
+pub fn load_presets() -> Presets {
+    load_toml("presets.toml", "presets")
+}
+
+pub fn save_presets(presets: &Presets) {
+    save_toml("presets.toml", "presets", presets);
+}
+
+/// Load the linked volume groups from `volume-groups.toml`.
+pub fn load_volume_groups() -> VolumeGroups {
+    load_toml::<VolumeGroupsFile>("volume-groups.toml", "volume groups").groups
+}
+
+pub fn save_volume_groups(groups: &VolumeGroups) {
+    save_toml("volume-groups.toml", "volume groups", &VolumeGroupsFile { groups: groups.clone() });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_persistence_overrides_survive_a_save() {
+        let mut session = Session {
+            window: Some(WindowGeometry {
+                width: 400.0,
+                height: 600.0,
+                x: None,
+                y: None,
+            }),
+            ..Session::default()
+        };
+        session.settings.set_persist_override("alsa_card.pci-0000:00:1f.3", false);
+
+        let text = toml::to_string(&session).unwrap();
+        let loaded: Session = toml::from_str(&text).unwrap();
+
+        assert_eq!(loaded.settings.persist_overrides, session.settings.persist_overrides);
+    }
+}
@@ -0,0 +1,124 @@
+//! Control socket at `$XDG_RUNTIME_DIR/copper.sock`, so other programs can drive a running
+//! Copper without starting a process per action.
+//!
+//! One command per line; every reply ends with a line that is `ok` or `error: <reason>`:
+//!
+//! ```text
+//! list                    one line per node, as printed by `copper --list`, then ok
+//! set-volume <node> <pct>
+//! mute <node> [on|off]    without on/off, toggles
+//! ```
+
+use crate::cli;
+use crate::state::{AppState, PwCommand};
+use crossbeam_channel::Sender;
+use parking_lot::Mutex;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Longest command line accepted; a client sending more is disconnected.
+const MAX_LINE: u64 = 1024;
+
+/// The bound socket; dropping it removes the socket file.
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Bind the socket and serve it from a background thread, one thread per client.
+/// Returns `None` if there is no runtime dir or another Copper already owns the socket.
+pub fn spawn(state: Arc<Mutex<AppState>>, tx: Sender<PwCommand>) -> Option<ControlSocket> {
+    let path = PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR")?).join("copper.sock");
+
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            log::warn!("Not opening the control socket: {} is in use by another Copper", path.display());
+            return None;
+        }
+        // Left behind by a Copper that didn't exit cleanly.
+        let _ = std::fs::remove_file(&path);
+    }
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::warn!("Failed to open the control socket {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let state = state.clone();
+            let tx = tx.clone();
+            std::thread::spawn(move || serve(stream, &state, &tx));
+        }
+    });
+
+    Some(ControlSocket { path })
+}
+
+/// Answer one client's commands until it disconnects or misbehaves.
+fn serve(stream: UnixStream, state: &Arc<Mutex<AppState>>, tx: &Sender<PwCommand>) {
+    let Ok(mut writer) = stream.try_clone() else { return };
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let mut line = String::new();
+        // Not valid UTF-8 is an error too, which ends the connection.
+        match reader.by_ref().take(MAX_LINE).read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) if !line.ends_with('\n') && line.len() as u64 >= MAX_LINE => {
+                let _ = writeln!(writer, "error: line too long");
+                return;
+            }
+            Ok(_) => {}
+        }
+
+        let reply = match handle(line.trim(), state, tx) {
+            Ok(output) => format!("{}ok\n", output),
+            // Replies are line-based; keep only the first line of multi-line errors.
+            Err(e) => format!("error: {}\n", e.lines().next().unwrap_or_default()),
+        };
+        if writer.write_all(reply.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Run one command, returning the lines to send before `ok`.
+fn handle(line: &str, state: &Arc<Mutex<AppState>>, tx: &Sender<PwCommand>) -> Result<String, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let command = match words.as_slice() {
+        [] => return Ok(String::new()),
+        ["list"] => return Ok(cli::format_list(&state.lock())),
+        ["set-volume", node, percent] => {
+            let volume = cli::parse_percent(percent)? / 100.0;
+            let s = state.lock();
+            PwCommand::SetVolume(cli::find_node(&s, node)?.id, volume)
+        }
+        ["mute", node, rest @ ..] => {
+            let s = state.lock();
+            let node = cli::find_node(&s, node)?;
+            let mute = match rest {
+                [] => !node.muted,
+                ["on"] => true,
+                ["off"] => false,
+                _ => return Err("expected: mute <node> [on|off]".to_string()),
+            };
+            PwCommand::SetMute(node.id, mute)
+        }
+        [command, ..] => return Err(format!("unknown command \"{}\"", command)),
+    };
+
+    tx.send(command).map_err(|_| "Copper is shutting down".to_string())?;
+    Ok(String::new())
+}
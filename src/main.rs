@@ -1,5 +1,9 @@
+mod cli;
+mod config;
+mod ipc;
 mod pipewire;
 mod state;
+mod tray;
 mod ui;
 
 use crossbeam_channel::unbounded;
@@ -13,33 +17,83 @@ use ui::CopperApp;
 fn main() -> Result<(), eframe::Error> {
     env_logger::init();
 
-    let state = Arc::new(Mutex::new(AppState::new()));
+    let config = config::load();
+    let default_tab = config.default_tab;
+    let session = config::load_session();
+    let mut app_state = AppState::new();
+    session.settings.apply_to(&mut app_state);
+    app_state.channel_overrides = config.channel_overrides;
+    app_state.volume_curve = config.volume_curve;
+    app_state.app_volumes = config::load_app_volumes();
+    app_state.volume_groups = config::load_volume_groups();
+    app_state.role_volumes = config
+        .role_volumes
+        .into_iter()
+        .map(|(role, percent)| (role.to_lowercase(), (percent / 100.0).clamp(0.0, 1.0)))
+        .collect();
+
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::take_remote(&mut args) {
+        Ok(remote) => app_state.remote = remote,
+        Err(e) => {
+            eprintln!("copper: {}", e);
+            std::process::exit(2);
+        }
+    }
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args, app_state));
+    }
+
+    let state = Arc::new(Mutex::new(app_state));
     let (tx_cmd, rx_cmd) = unbounded::<PwCommand>();
     let repaint_ctx = Arc::new(Mutex::new(None::<egui::Context>));
 
     // Spawn PipeWire thread
-    {
+    let pipewire_thread = {
         let state = state.clone();
         let repaint_ctx = repaint_ctx.clone();
         std::thread::spawn(move || {
-            pipewire::run(state, rx_cmd, repaint_ctx);
-        });
+            pipewire::run(state, rx_cmd, repaint_ctx, pipewire::Mode::Gui);
+        })
+    };
+    let tx_quit = tx_cmd.clone();
+    let _control_socket = ipc::spawn(state.clone(), tx_cmd.clone());
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([400.0, 600.0])
+        .with_min_inner_size([300.0, 200.0])
+        .with_transparent(false);
+    if let Some(window) = session.window {
+        viewport = viewport.with_inner_size([window.width, window.height]);
+        if let (Some(x), Some(y)) = (window.x, window.y) {
+            viewport = viewport.with_position([x, y]);
+        }
+    }
+    if session.always_on_top {
+        viewport = viewport.with_always_on_top();
+    }
+    if session.borderless {
+        viewport = viewport.with_decorations(false);
     }
 
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([400.0, 600.0])
-            .with_min_inner_size([300.0, 200.0])
-            .with_transparent(false),
+        viewport,
         ..Default::default()
     };
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "Copper",
         options,
         Box::new(move |cc| {
             *repaint_ctx.lock() = Some(cc.egui_ctx.clone());
-            Ok(Box::new(CopperApp::new(state, tx_cmd)))
+            ui::apply_theme(&cc.egui_ctx, session.theme);
+            let tray = tray::Tray::spawn(state.clone(), tx_cmd.clone(), cc.egui_ctx.clone());
+            Ok(Box::new(CopperApp::new(state, tx_cmd, default_tab, session, tray)))
         }),
-    )
+    );
+
+    // Let the PipeWire thread close its connection cleanly before the process ends.
+    let _ = tx_quit.send(PwCommand::Quit);
+    let _ = pipewire_thread.join();
+    result
 }
@@ -0,0 +1,158 @@
+//! Short audible "blip" played on a sink after its volume changes.
+
+use super::spa;
+use crate::state::AppState;
+use libspa as spa_lib;
+use parking_lot::Mutex;
+use pipewire as pw;
+use std::cell::{Cell, RefCell};
+use std::f32::consts::TAU;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+const RATE: u32 = 48000;
+const CHANNELS: usize = 2;
+const STRIDE: usize = CHANNELS * std::mem::size_of::<f32>();
+
+const TONE_HZ: f32 = 880.0;
+const TONE_LENGTH: Duration = Duration::from_millis(60);
+const TONE_GAIN: f32 = 0.25;
+/// Minimum gap between sounds, so dragging a slider doesn't produce a buzz.
+const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Sample generator shared with the stream's process callback.
+struct Tone {
+    position: usize,
+    length: usize,
+    /// Set on PipeWire's realtime data thread (`RT_PROCESS`), read on the main loop.
+    done: Arc<AtomicBool>,
+}
+
+impl Tone {
+    fn next_sample(&mut self) -> f32 {
+        if self.position >= self.length {
+            self.done.store(true, Ordering::Release);
+            return 0.0;
+        }
+
+        let t = self.position as f32 / RATE as f32;
+        // Fade in and out over the whole tone to avoid clicks.
+        let envelope = (std::f32::consts::PI * self.position as f32 / self.length as f32).sin();
+        self.position += 1;
+        (TAU * TONE_HZ * t).sin() * envelope * TONE_GAIN
+    }
+}
+
+/// A feedback stream that is still playing.
+struct Playing {
+    _listener: pw::stream::StreamListener<Tone>,
+    stream: pw::stream::StreamRc,
+    done: Arc<AtomicBool>,
+}
+
+pub struct Feedback {
+    core: pw::core::CoreRc,
+    playing: RefCell<Option<Playing>>,
+    last_played: Cell<Option<Instant>>,
+}
+
+impl Feedback {
+    pub fn new(core: pw::core::CoreRc) -> Self {
+        Self {
+            core,
+            playing: RefCell::new(None),
+            last_played: Cell::new(None),
+        }
+    }
+
+    /// Play the feedback sound on a device sink whose volume was just changed, if enabled.
+    pub fn volume_changed(&self, node_id: u32, state: &Arc<Mutex<AppState>>) {
+        let sink_name = {
+            let s = state.lock();
+            if !s.volume_feedback_sound {
+                return;
+            }
+            let Some(node) = s.nodes.get(&node_id).filter(|n| n.is_sink && !n.is_stream) else { return };
+            node.name.clone()
+        };
+
+        if self.last_played.get().is_some_and(|at| at.elapsed() < MIN_INTERVAL) {
+            return;
+        }
+        self.last_played.set(Some(Instant::now()));
+
+        if let Err(e) = self.play(&sink_name) {
+            log::warn!("Failed to play volume feedback on {}: {}", sink_name, e);
+        }
+    }
+
+    /// Disconnect the stream once its tone has been played out.
+    pub fn reap(&self) {
+        if let Some(p) = self.playing.borrow_mut().take_if(|p| p.done.load(Ordering::Acquire)) {
+            let _ = p.stream.disconnect();
+        }
+    }
+
+    fn play(&self, sink_name: &str) -> Result<(), pw::Error> {
+        let done = Arc::new(AtomicBool::new(false));
+        let tone = Tone {
+            position: 0,
+            length: (RATE as f32 * TONE_LENGTH.as_secs_f32()) as usize,
+            done: done.clone(),
+        };
+
+        let stream = pw::stream::StreamRc::new(
+            self.core.clone(),
+            "copper-volume-feedback",
+            pw::properties::properties! {
+                "media.type" => "Audio",
+                "media.category" => "Playback",
+                "media.role" => "Event",
+                "target.object" => sink_name,
+            },
+        )?;
+
+        let listener = stream
+            .add_local_listener_with_user_data(tone)
+            .process(|stream, tone| {
+                let Some(mut buffer) = stream.dequeue_buffer() else { return };
+                let datas = buffer.datas_mut();
+                let Some(data) = datas.first_mut() else { return };
+
+                let mut frames = 0;
+                if let Some(slice) = data.data() {
+                    frames = slice.len() / STRIDE;
+                    for frame in slice.chunks_exact_mut(STRIDE) {
+                        let sample = tone.next_sample().to_le_bytes();
+                        for channel in frame.chunks_exact_mut(std::mem::size_of::<f32>()) {
+                            channel.copy_from_slice(&sample);
+                        }
+                    }
+                }
+
+                let chunk = data.chunk_mut();
+                *chunk.offset_mut() = 0;
+                *chunk.stride_mut() = STRIDE as i32;
+                *chunk.size_mut() = (frames * STRIDE) as u32;
+            })
+            .register()?;
+
+        let Some(format) = spa::build_audio_format_pod(RATE, CHANNELS as u32) else { return Ok(()) };
+        let Some(format) = spa_lib::pod::Pod::from_bytes(&format) else { return Ok(()) };
+        stream.connect(
+            spa_lib::utils::Direction::Output,
+            None,
+            pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS | pw::stream::StreamFlags::RT_PROCESS,
+            &mut [format],
+        )?;
+
+        // Replacing an unfinished sound drops (and so disconnects) the old stream.
+        *self.playing.borrow_mut() = Some(Playing {
+            _listener: listener,
+            stream,
+            done,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! Level meters: a passive capture stream on each device that records its peak level.
+
+use super::{Repaint, spa};
+use crate::state::AppState;
+use libspa as spa_lib;
+use parking_lot::Mutex;
+use pipewire as pw;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const RATE: u32 = 48000;
+const CHANNELS: u32 = 2;
+const SAMPLE_SIZE: usize = std::mem::size_of::<f32>();
+/// Peaks below this are treated as silence, so idle devices don't keep the UI repainting.
+const SILENCE: f32 = 0.0005;
+/// How fast the peak-hold marker falls, in dB per second: about 1.5 s from full scale to silence.
+const PEAK_HOLD_DECAY_DB: f32 = 44.0;
+
+/// A meter stream; dropping it disconnects the stream.
+struct Meter {
+    _listener: pw::stream::StreamListener<u32>,
+    stream: pw::stream::StreamRc,
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        let _ = self.stream.disconnect();
+    }
+}
+
+/// Meter streams exist only while meters are shown, so hidden meters cost nothing.
+pub struct Meters {
+    core: pw::core::CoreRc,
+    streams: RefCell<HashMap<u32, Meter>>,
+    shown: Cell<bool>,
+}
+
+impl Meters {
+    pub fn new(core: pw::core::CoreRc) -> Self {
+        Self {
+            core,
+            streams: RefCell::new(HashMap::new()),
+            shown: Cell::new(false),
+        }
+    }
+
+    /// Start or stop metering every device when the `meters_shown` setting changes.
+    pub fn follow_setting(&self, state: &Arc<Mutex<AppState>>, repaint: &Repaint) {
+        let shown = state.lock().meters_shown;
+        if self.shown.replace(shown) == shown {
+            return;
+        }
+
+        if shown {
+            let ids: Vec<u32> = state.lock().nodes.values().filter(|n| !n.is_stream).map(|n| n.id).collect();
+            for id in ids {
+                self.add(id, state, repaint);
+            }
+        } else {
+            self.streams.borrow_mut().clear();
+            // Otherwise the last levels would show up again when meters are turned back on.
+            for node in state.lock().nodes.values_mut() {
+                node.peak = 0.0;
+                node.peak_hold = 0.0;
+            }
+        }
+    }
+
+    /// Start metering a device node if meters are shown, recording on the node whether that worked.
+    pub fn add(&self, node_id: u32, state: &Arc<Mutex<AppState>>, repaint: &Repaint) {
+        if !self.shown.get() {
+            return;
+        }
+        let (name, is_sink) = {
+            let s = state.lock();
+            let Some(node) = s.nodes.get(&node_id).filter(|n| !n.is_stream) else { return };
+            (node.name.clone(), node.is_sink)
+        };
+
+        let available = match self.connect(node_id, &name, is_sink, state, repaint) {
+            Ok(meter) => {
+                self.streams.borrow_mut().insert(node_id, meter);
+                true
+            }
+            Err(e) => {
+                log::warn!("Failed to attach a level meter to {}: {}", name, e);
+                false
+            }
+        };
+
+        if let Some(node) = state.lock().nodes.get_mut(&node_id) {
+            node.meter_available = available;
+        }
+    }
+
+    pub fn remove(&self, node_id: u32) {
+        self.streams.borrow_mut().remove(&node_id);
+    }
+
+    fn connect(
+        &self,
+        node_id: u32,
+        name: &str,
+        is_sink: bool,
+        state: &Arc<Mutex<AppState>>,
+        repaint: &Repaint,
+    ) -> Result<Meter, pw::Error> {
+        let mut props = pw::properties::properties! {
+            "media.type" => "Audio",
+            "media.category" => "Capture",
+            "media.role" => "DSP",
+            // Passive, so metering never keeps an otherwise idle device running.
+            "node.passive" => "true",
+            "target.object" => name,
+        };
+        if is_sink {
+            props.insert("stream.capture.sink", "true");
+        }
+
+        let stream = pw::stream::StreamRc::new(self.core.clone(), "copper-level-meter", props)?;
+
+        let state_error = state.clone();
+        let state = state.clone();
+        let repaint = repaint.clone();
+        let listener = stream
+            .add_local_listener_with_user_data(node_id)
+            .state_changed(move |_, node_id, _, new| {
+                if let pw::stream::StreamState::Error(message) = new {
+                    log::warn!("Level meter for node {} failed: {}", node_id, message);
+                    if let Some(node) = state_error.lock().nodes.get_mut(node_id) {
+                        node.meter_available = false;
+                        node.peak = 0.0;
+                        node.peak_hold = 0.0;
+                    }
+                }
+            })
+            .process(move |stream, node_id| {
+                let Some(mut buffer) = stream.dequeue_buffer() else { return };
+                let datas = buffer.datas_mut();
+                let Some(data) = datas.first_mut() else { return };
+
+                let offset = data.chunk().offset() as usize;
+                let size = data.chunk().size() as usize;
+                let Some(bytes) = data.data() else { return };
+                let Some(bytes) = bytes.get(offset..offset + size) else { return };
+
+                let peak = bytes
+                    .chunks_exact(SAMPLE_SIZE)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).abs())
+                    .fold(0.0, f32::max);
+                // The hold decays by the buffer's duration, so it follows the audio clock.
+                let duration = size as f32 / (SAMPLE_SIZE as u32 * CHANNELS * RATE) as f32;
+                let decay = 10f32.powf(-PEAK_HOLD_DECAY_DB * duration / 20.0);
+
+                let (was_silent, hold) = {
+                    let mut s = state.lock();
+                    let Some(node) = s.nodes.get_mut(node_id) else { return };
+                    let was_silent = node.peak < SILENCE && node.peak_hold < SILENCE;
+                    node.peak = peak;
+                    node.peak_hold = peak.max(node.peak_hold * decay);
+                    (was_silent, node.peak_hold)
+                };
+
+                if hold >= SILENCE || !was_silent {
+                    repaint.changed();
+                }
+            })
+            .register()?;
+
+        let format = spa::build_audio_format_pod(RATE, CHANNELS).ok_or(pw::Error::CreationFailed)?;
+        let format = spa_lib::pod::Pod::from_bytes(&format).ok_or(pw::Error::CreationFailed)?;
+        stream.connect(
+            spa_lib::utils::Direction::Input,
+            None,
+            pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+            &mut [format],
+        )?;
+
+        Ok(Meter {
+            _listener: listener,
+            stream,
+        })
+    }
+}
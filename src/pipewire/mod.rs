@@ -1,90 +1,352 @@
 //! PipeWire backend for audio device management.
 
+mod feedback;
+mod meter;
+mod notify;
 mod spa;
 
-use crate::state::{AppState, AudioNode, PwCommand};
-use crossbeam_channel::Receiver;
+use crate::state::{ActiveRoute, AppState, AudioFormat, Card, AudioNode, LinkInfo, LinkState, NodeKind, NodeLatency, NodeState, PortInfo, PwCommand, VolumeCurve, VolumeRequest, stereo_levels};
+use crossbeam_channel::{Receiver, RecvTimeoutError};
 use eframe::egui;
 use libspa as spa_lib;
 use parking_lot::Mutex;
 use pipewire as pw;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// How long after setting a stream volume an app reset is treated as an override.
+const VOLUME_REVERT_WINDOW: Duration = Duration::from_millis(1500);
+/// Tolerance when comparing a reported volume with the one we requested.
+const VOLUME_EPSILON: f32 = 0.005;
+/// How long a volume we sent is remembered, to drop its echo once a newer one is shown.
+const VOLUME_IN_FLIGHT: Duration = Duration::from_millis(500);
+/// How long a device gets to report a requested mute before we assume it ignores it.
+const MUTE_CONFIRM_TIMEOUT: Duration = Duration::from_millis(500);
+/// First wait before reconnecting to PipeWire; doubles on each failed attempt.
+const RECONNECT_DELAY_MIN: Duration = Duration::from_millis(500);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(10);
+/// How long app volumes have to stay unchanged before they are written out.
+const APP_VOLUME_SAVE_DELAY: Duration = Duration::from_secs(2);
+/// Toasts kept at most; older ones are dropped first.
+const MAX_MESSAGES: usize = 5;
+/// WirePlumber's default route volumes (`device.routes.default-sink-volume` and
+/// `default-source-volume`), as linear gain: 40% on the cubic scale for outputs, full for inputs.
+const DEFAULT_OUTPUT_GAIN: f32 = 0.064;
+const DEFAULT_INPUT_GAIN: f32 = 1.0;
+/// Shortest time between repaints we ask for, about 30 frames per second.
+const MIN_REPAINT_INTERVAL: Duration = Duration::from_millis(33);
+
+// Fields drop in declaration order: each listener must be unhooked before its
+// proxy is destroyed, otherwise removing the hook touches freed proxy memory.
 
 struct NodeWrapper {
-    proxy: pw::node::Node,
     _listener: Box<dyn pw::proxy::Listener>,
+    proxy: pw::node::Node,
 }
 
 struct DeviceWrapper {
-    proxy: pw::device::Device,
     _listener: Box<dyn pw::proxy::Listener>,
+    proxy: pw::device::Device,
 }
 
 struct MetadataWrapper {
+    _listener: Box<dyn pw::proxy::Listener>,
     proxy: pw::metadata::Metadata,
+    /// `metadata.name`: "default" for devices and stream targets, "settings" for the clock.
+    name: String,
+}
+
+struct LinkWrapper {
     _listener: Box<dyn pw::proxy::Listener>,
+    _proxy: pw::link::Link,
 }
 
 type NodeMap = Rc<RefCell<HashMap<u32, NodeWrapper>>>;
+type LinkMap = Rc<RefCell<HashMap<u32, LinkWrapper>>>;
 type DeviceMap = Rc<RefCell<HashMap<u32, DeviceWrapper>>>;
 type MetadataMap = Rc<RefCell<HashMap<u32, MetadataWrapper>>>;
+/// Set when default device metadata changed; handled once per timer tick.
+type DirtyFlag = Rc<Cell<bool>>;
+/// When the per-app volumes were last changed without being saved yet.
+type PendingSave = Rc<Cell<Option<Instant>>>;
+type Repaint = Rc<RepaintState>;
+
+/// Who the PipeWire thread works for.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// The window: devices are metered, and new streams get role and app volumes and
+    /// may unmute the input for a call.
+    Gui,
+    /// A one-off command line action, which must leave everything else as it was.
+    Cli,
+}
 
 /// Main PipeWire thread entry point.
 pub fn run(
     state: Arc<Mutex<AppState>>,
     rx: Receiver<PwCommand>,
     repaint_ctx: Arc<Mutex<Option<egui::Context>>>,
+    mode: Mode,
 ) {
     pw::init();
+    serve(&state, &rx, &Rc::new(RepaintState::new(repaint_ctx)), mode);
+    // Every proxy, stream, listener and the loop itself were dropped with `serve`.
+    unsafe { pw::deinit() };
+}
+
+/// Why a session ended.
+#[derive(Clone, Copy, PartialEq)]
+enum SessionEnd {
+    Quit,
+    /// The daemon went away or the connection broke.
+    Disconnected,
+    /// The user asked for everything to be fetched again.
+    Rescan,
+}
 
+/// Stay connected to PipeWire until Quit, reconnecting whenever the daemon goes away.
+fn serve(state: &Arc<Mutex<AppState>>, rx: &Receiver<PwCommand>, repaint: &Repaint, mode: Mode) {
     let mainloop = pw::main_loop::MainLoopRc::new(None).expect("Failed to create MainLoop");
     let context = pw::context::ContextRc::new(&mainloop, None).expect("Failed to create Context");
-    let core = context.connect_rc(None).expect("Failed to connect to Core");
-    let registry = core.get_registry_rc().expect("Failed to get Registry");
+    let mut delay = RECONNECT_DELAY_MIN;
+    // Nodes from before a rescan, which are not new to the graph.
+    let mut known = HashSet::new();
+    // Without a name, libpipewire picks PIPEWIRE_REMOTE or the default instance.
+    let remote = state.lock().remote.clone();
+
+    loop {
+        let props = remote
+            .as_deref()
+            .map(|name| pw::properties::properties! { "remote.name" => name });
+        match context.connect_rc(props) {
+            Ok(core) => {
+                state.lock().connected = true;
+                repaint.now();
+                delay = RECONNECT_DELAY_MIN;
+                match run_session(&mainloop, core, state, rx, repaint, &known, mode) {
+                    SessionEnd::Quit => return,
+                    SessionEnd::Rescan => {
+                        log::info!("Rescanning the PipeWire graph");
+                        known = state.lock().nodes.keys().copied().collect();
+                        forget_graph(state);
+                        continue;
+                    }
+                    SessionEnd::Disconnected => log::warn!("Lost the connection to PipeWire, reconnecting"),
+                }
+            }
+            Err(e) => log::warn!("Failed to connect to PipeWire: {}", e),
+        }
+
+        known.clear();
+        forget_graph(state);
+        state.lock().connected = false;
+        repaint.now();
+
+        // Keep answering Quit while waiting; everything else needs a connection.
+        let deadline = Instant::now() + delay;
+        loop {
+            match rx.recv_deadline(deadline) {
+                Ok(PwCommand::Quit) | Err(RecvTimeoutError::Disconnected) => return,
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => break,
+            }
+        }
+        delay = (delay * 2).min(RECONNECT_DELAY_MAX);
+    }
+}
+
+/// Drop everything learned from a connection; the next one reports it afresh.
+fn forget_graph(state: &Arc<Mutex<AppState>>) {
+    let mut s = state.lock();
+    s.synced = false;
+    s.nodes.clear();
+    s.cards.clear();
+    s.default_sink_name = None;
+    s.default_source_name = None;
+    s.configured_sink_name = None;
+    s.configured_source_name = None;
+    s.links.clear();
+    s.ports.clear();
+}
+
+/// Track the graph over one connection, until the core reports it broken, Quit arrives
+/// or a rescan is requested. Nodes in `known` were seen before a rescan and aren't
+/// treated as new streams.
+fn run_session(
+    mainloop: &pw::main_loop::MainLoopRc,
+    core: pw::core::CoreRc,
+    state: &Arc<Mutex<AppState>>,
+    rx: &Receiver<PwCommand>,
+    repaint: &Repaint,
+    known: &HashSet<u32>,
+    mode: Mode,
+) -> SessionEnd {
+    let registry = match core.get_registry_rc() {
+        Ok(registry) => registry,
+        Err(e) => {
+            log::warn!("Failed to get the PipeWire registry: {}", e);
+            return SessionEnd::Disconnected;
+        }
+    };
 
     let nodes: NodeMap = Rc::new(RefCell::new(HashMap::new()));
     let devices: DeviceMap = Rc::new(RefCell::new(HashMap::new()));
     let metadata: MetadataMap = Rc::new(RefCell::new(HashMap::new()));
+    let links: LinkMap = Rc::new(RefCell::new(HashMap::new()));
+    let defaults_dirty: DirtyFlag = Rc::new(Cell::new(false));
+    let app_volumes_changed: PendingSave = Rc::new(Cell::new(None));
+    let end = Rc::new(Cell::new(SessionEnd::Disconnected));
+    let feedback = feedback::Feedback::new(core.clone());
+    let notifier = notify::Notifier::new();
+    let meters = Rc::new(meter::Meters::new(core.clone()));
+
+    // Two round trips make a full snapshot: the first lists every global, the second lets
+    // the objects bound for them report their params.
+    let pending_sync: Rc<Cell<Option<pw::core::AsyncSeq>>> = Rc::new(Cell::new(None));
+
+    // A daemon restart shows up as an error on the core object itself.
+    let _core_listener = {
+        let mainloop = mainloop.clone();
+        let core_sync = core.clone();
+        let pending_sync = pending_sync.clone();
+        let first_round = Cell::new(true);
+        let state_error = state.clone();
+        let repaint_error = repaint.clone();
+        let state = state.clone();
+        core.add_listener_local()
+            .done(move |id, seq| {
+                if id != pw::core::PW_ID_CORE || pending_sync.get() != Some(seq) {
+                    return;
+                }
+                if first_round.replace(false) {
+                    pending_sync.set(core_sync.sync(0).ok());
+                } else {
+                    pending_sync.set(None);
+                    state.lock().synced = true;
+                }
+            })
+            .error(move |id, _seq, res, message| {
+                if id == pw::core::PW_ID_CORE {
+                    log::warn!("PipeWire connection error {}: {}", res, message);
+                    mainloop.quit();
+                } else {
+                    // E.g. a link that couldn't be created, or a global we may not destroy.
+                    report_error(&state_error, &repaint_error, format!("PipeWire error on object {}: {}", id, message));
+                }
+            })
+            .register()
+    };
 
     // Setup registry listener
     let _registry_listener = {
         let registry_clone = registry.clone();
         let state_add = state.clone();
-        let repaint_add = repaint_ctx.clone();
+        let repaint_add = repaint.clone();
         let nodes_add = nodes.clone();
         let devices_add = devices.clone();
         let metadata_add = metadata.clone();
+        let links_add = links.clone();
+        let defaults_dirty_add = defaults_dirty.clone();
+        let meters_add = meters.clone();
+        let known = known.clone();
 
         let state_remove = state.clone();
-        let repaint_remove = repaint_ctx.clone();
+        let repaint_remove = repaint.clone();
         let nodes_remove = nodes.clone();
         let devices_remove = devices.clone();
         let metadata_remove = metadata.clone();
+        let links_remove = links.clone();
+        let meters_remove = meters.clone();
 
         registry
             .add_listener_local()
             .global(move |global| {
-                handle_global_add(global, &registry_clone, &state_add, &repaint_add, &nodes_add, &devices_add, &metadata_add);
+                handle_global_add(
+                    global,
+                    &registry_clone,
+                    &state_add,
+                    &repaint_add,
+                    &nodes_add,
+                    &devices_add,
+                    &metadata_add,
+                    &links_add,
+                    &defaults_dirty_add,
+                    &meters_add,
+                    &known,
+                    mode,
+                );
             })
             .global_remove(move |id| {
-                handle_global_remove(id, &state_remove, &repaint_remove, &nodes_remove, &devices_remove, &metadata_remove);
+                handle_global_remove(
+                    id,
+                    &state_remove,
+                    &repaint_remove,
+                    &nodes_remove,
+                    &devices_remove,
+                    &metadata_remove,
+                    &links_remove,
+                    &meters_remove,
+                );
             })
             .register()
     };
 
+    pending_sync.set(core.sync(0).ok());
+
     // Setup command timer
     let timer = {
+        let core = core.clone();
+        let registry = registry.clone();
         let rx = rx.clone();
         let state = state.clone();
         let devices = devices.clone();
         let metadata = metadata.clone();
+        let app_volumes_changed = app_volumes_changed.clone();
+        let end = end.clone();
+        let quit_loop = mainloop.clone();
+        let repaint = repaint.clone();
+        let meters = meters.clone();
 
         mainloop.loop_().add_timer(move |_| {
-            process_commands(&rx, &state, &nodes, &devices, &metadata);
+            if let Some(reason) = process_commands(
+                &rx,
+                &core,
+                &registry,
+                &state,
+                &repaint,
+                &nodes,
+                &devices,
+                &metadata,
+                &feedback,
+                &notifier,
+                &app_volumes_changed,
+            ) {
+                end.set(reason);
+                quit_loop.quit();
+                return;
+            }
+            feedback.reap();
+            notifier.tick(&state);
+            check_ignored_mutes(&state, &nodes, &devices);
+            if mode == Mode::Gui {
+                meters.follow_setting(&state, &repaint);
+            }
+
+            if app_volumes_changed.get().is_some_and(|at| at.elapsed() > APP_VOLUME_SAVE_DELAY) {
+                save_app_volumes(&state, &app_volumes_changed);
+            }
+
+            // Several default keys often change together; recompute once per tick.
+            if defaults_dirty.replace(false) {
+                refresh_defaults(&state);
+                repaint.changed();
+            }
+            // Changes that came too soon after the last repaint are shown now.
+            repaint.flush();
         })
     };
 
@@ -94,49 +356,168 @@ pub fn run(
         .unwrap();
 
     mainloop.run();
+
+    // Changes still waiting for the save delay would otherwise be lost.
+    if app_volumes_changed.get().is_some() {
+        save_app_volumes(state, &app_volumes_changed);
+    }
+    end.get()
 }
 
 // --- Global Handlers ---
 
+#[allow(clippy::too_many_arguments)]
 fn handle_global_add(
     global: &pw::registry::GlobalObject<&pw::spa::utils::dict::DictRef>,
     registry: &pw::registry::RegistryRc,
     state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
+    repaint: &Repaint,
     nodes: &NodeMap,
     devices: &DeviceMap,
     metadata: &MetadataMap,
+    links: &LinkMap,
+    defaults_dirty: &DirtyFlag,
+    meters: &meter::Meters,
+    known: &HashSet<u32>,
+    mode: Mode,
 ) {
     let Some(props) = global.props else { return };
 
     if global.type_ == pw::types::ObjectType::Device {
         handle_device(global, props, registry, state, repaint, devices);
     } else if global.type_ == pw::types::ObjectType::Metadata {
-        handle_metadata(global, props, registry, state, repaint, metadata);
+        handle_metadata(global, props, registry, state, repaint, metadata, defaults_dirty);
+    } else if global.type_ == pw::types::ObjectType::Link {
+        handle_link(global, props, registry, state, repaint, links);
+    } else if global.type_ == pw::types::ObjectType::Port {
+        handle_port(global, props, state);
     } else {
         handle_node(global, props, registry, state, repaint, nodes);
+        if mode == Mode::Gui {
+            meters.add(global.id, state, repaint);
+            // Streams that were already there when we connected keep the volume they have.
+            let added_after_sync = state.lock().synced;
+            if !known.contains(&global.id) {
+                if added_after_sync {
+                    apply_role_volume(global.id, props, state, nodes, devices);
+                    restore_app_volume(global.id, state, nodes, devices);
+                }
+                unmute_source_for_call(global.id, props, state, nodes, devices);
+            }
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_global_remove(
     id: u32,
     state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
+    repaint: &Repaint,
     nodes: &NodeMap,
     devices: &DeviceMap,
     metadata: &MetadataMap,
+    links: &LinkMap,
+    meters: &meter::Meters,
 ) {
+    // Dropping the wrappers releases the proxy and its listener right away.
     nodes.borrow_mut().remove(&id);
+    meters.remove(id);
     devices.borrow_mut().remove(&id);
     metadata.borrow_mut().remove(&id);
+    links.borrow_mut().remove(&id);
 
-    let mut s = state.lock();
+    if forget_global(&mut state.lock(), id) {
+        repaint.changed();
+    }
+}
+
+/// Drop whatever the state holds for a removed global. Returns whether anything shown changed.
+fn forget_global(s: &mut AppState, id: u32) -> bool {
     let mut changed = s.nodes.remove(&id).is_some();
     changed |= s.cards.remove(&id).is_some();
+    changed |= s.links.remove(&id).is_some();
+    s.ports.remove(&id);
+    changed
+}
 
-    if changed {
-        request_repaint(repaint);
-    }
+// --- Link Handling ---
+
+fn handle_link(
+    global: &pw::registry::GlobalObject<&pw::spa::utils::dict::DictRef>,
+    props: &pw::spa::utils::dict::DictRef,
+    registry: &pw::registry::RegistryRc,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+    links: &LinkMap,
+) {
+    let id_prop = |key| props.get(key).and_then(|v| v.parse::<u32>().ok());
+    let (Some(output_node), Some(output_port), Some(input_node), Some(input_port)) = (
+        id_prop("link.output.node"),
+        id_prop("link.output.port"),
+        id_prop("link.input.node"),
+        id_prop("link.input.port"),
+    ) else {
+        return;
+    };
+
+    let id = global.id;
+    state.lock().links.insert(
+        id,
+        LinkInfo {
+            output_node,
+            output_port,
+            input_node,
+            input_port,
+            state: LinkState::Connecting,
+        },
+    );
+    repaint.changed();
+
+    // Bound only to follow the link's state; the ends never change.
+    let Ok(proxy) = registry.bind::<pw::link::Link, _>(global) else { return };
+    let state = state.clone();
+    let repaint = repaint.clone();
+    let listener = proxy
+        .add_listener_local()
+        .info(move |info| {
+            let link_state = match info.state() {
+                pw::link::LinkState::Active => LinkState::Active,
+                pw::link::LinkState::Paused => LinkState::Paused,
+                pw::link::LinkState::Error(message) => LinkState::Error(message.to_string()),
+                _ => LinkState::Connecting,
+            };
+            let mut s = state.lock();
+            let Some(link) = s.links.get_mut(&id) else { return };
+            if link.state != link_state {
+                link.state = link_state;
+                drop(s);
+                repaint.changed();
+            }
+        })
+        .register();
+
+    links.borrow_mut().insert(
+        id,
+        LinkWrapper {
+            _listener: Box::new(listener),
+            _proxy: proxy,
+        },
+    );
+}
+
+fn handle_port(
+    global: &pw::registry::GlobalObject<&pw::spa::utils::dict::DictRef>,
+    props: &pw::spa::utils::dict::DictRef,
+    state: &Arc<Mutex<AppState>>,
+) {
+    let Some(node_id) = props.get("node.id").and_then(|v| v.parse::<u32>().ok()) else { return };
+    let port = PortInfo {
+        node_id,
+        is_output: props.get("port.direction") == Some("out"),
+        name: props.get("port.name").unwrap_or("port").to_string(),
+        channel: props.get("audio.channel").map(str::to_string),
+    };
+    state.lock().ports.insert(global.id, port);
 }
 
 // --- Device Handling ---
@@ -146,7 +527,7 @@ fn handle_device(
     props: &pw::spa::utils::dict::DictRef,
     registry: &pw::registry::RegistryRc,
     state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
+    repaint: &Repaint,
     devices: &DeviceMap,
 ) {
     let media_class = props.get("media.class").unwrap_or("");
@@ -155,7 +536,13 @@ fn handle_device(
     }
 
     let device_id = global.id;
-    let device: pw::device::Device = registry.bind(global).expect("Failed to bind device");
+    let device: pw::device::Device = match registry.bind(global) {
+        Ok(device) => device,
+        Err(e) => {
+            report_error(state, repaint, format!("Failed to bind device {}: {}", device_id, e));
+            return;
+        }
+    };
 
     let name = props.get("device.name").unwrap_or("Unknown").to_string();
     let description = props.get("device.description").unwrap_or(&name).to_string();
@@ -166,9 +553,12 @@ fn handle_device(
             device_id,
             crate::state::Card {
                 id: device_id,
+                name,
                 description,
                 profiles: Vec::new(),
                 active_profile_index: None,
+                routes: Vec::new(),
+                active_routes: HashMap::new(),
             },
         );
     }
@@ -185,6 +575,7 @@ fn handle_device(
 
     device.subscribe_params(&[
         spa_lib::param::ParamType::Route,
+        spa_lib::param::ParamType::EnumRoute,
         spa_lib::param::ParamType::EnumProfile,
         spa_lib::param::ParamType::Profile,
     ]);
@@ -192,8 +583,8 @@ fn handle_device(
     devices.borrow_mut().insert(
         device_id,
         DeviceWrapper {
-            proxy: device,
             _listener: Box::new(listener),
+            proxy: device,
         },
     );
 }
@@ -203,27 +594,34 @@ fn on_device_param(
     param_id: spa_lib::param::ParamType,
     param: Option<&spa_lib::pod::Pod>,
     state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
+    repaint: &Repaint,
 ) {
     let Some(param) = param else { return };
 
     match param_id {
         spa_lib::param::ParamType::Route => {
-            if let Some(route) = unsafe { spa::parse_route(param.as_raw_ptr()) } {
-                update_node_from_route(device_id, &route, state);
-                request_repaint(repaint);
+            if let Some(route) = unsafe { spa::parse_route(param.as_raw_ptr()) }
+                && update_node_from_route(device_id, &route, state)
+            {
+                repaint.changed();
+            }
+        }
+        spa_lib::param::ParamType::EnumRoute => {
+            if let Some(route) = unsafe { spa::parse_enum_route(param.as_raw_ptr()) } {
+                update_card_from_enum_route(device_id, route, state);
+                repaint.changed();
             }
         }
         spa_lib::param::ParamType::EnumProfile => {
             if let Some(profile) = unsafe { spa::parse_profile(param.as_raw_ptr()) } {
                 update_card_from_enum_profile(device_id, profile, state);
-                request_repaint(repaint);
+                repaint.changed();
             }
         }
         spa_lib::param::ParamType::Profile => {
             if let Some(profile) = unsafe { spa::parse_profile(param.as_raw_ptr()) } {
                 update_card_from_profile(device_id, profile, state);
-                request_repaint(repaint);
+                repaint.changed();
             }
         }
         _ => {}
@@ -236,6 +634,7 @@ fn update_card_from_enum_profile(device_id: u32, profile: spa::ParsedProfile, st
         let p = crate::state::Profile {
             index: profile.index,
             description: profile.description,
+            priority: profile.priority,
             available: profile.available,
         };
 
@@ -248,15 +647,62 @@ fn update_card_from_enum_profile(device_id: u32, profile: spa::ParsedProfile, st
     }
 }
 
+fn update_card_from_enum_route(device_id: u32, route: spa::ParsedEnumRoute, state: &Arc<Mutex<AppState>>) {
+    let mut s = state.lock();
+    if let Some(card) = s.cards.get_mut(&device_id) {
+        let r = crate::state::Route {
+            index: route.index,
+            direction: route.direction,
+            description: route.description,
+            available: route.available,
+            profiles: route.profiles,
+            devices: route.devices,
+        };
+
+        if let Some(existing) = card.routes.iter_mut().find(|r| r.index == route.index) {
+            *existing = r;
+        } else {
+            card.routes.push(r);
+            card.routes.sort_by_key(|r| r.index);
+        }
+    }
+}
+
 fn update_card_from_profile(device_id: u32, profile: spa::ParsedProfile, state: &Arc<Mutex<AppState>>) {
     let mut s = state.lock();
     if let Some(card) = s.cards.get_mut(&device_id) {
+        // A new profile brings its own route devices; their Route params follow.
+        if card.active_profile_index != Some(profile.index) {
+            card.active_routes.clear();
+        }
         card.active_profile_index = Some(profile.index);
     }
 }
 
-fn update_node_from_route(device_id: u32, route: &spa::ParsedRoute, state: &Arc<Mutex<AppState>>) {
+/// Returns whether the active port or any node's levels changed.
+fn update_node_from_route(device_id: u32, route: &spa::ParsedRoute, state: &Arc<Mutex<AppState>>) -> bool {
     let mut s = state.lock();
+    let s = &mut *s;
+    let curve = s.volume_curve;
+
+    // With one route per direction, a node that doesn't name its route device can only be
+    // on that route; with several it is ambiguous and left alone.
+    let mut changed = false;
+    let sole_route = match s.cards.get_mut(&device_id) {
+        Some(card) => {
+            let previous = card.active_routes.insert(
+                route.route_device,
+                ActiveRoute {
+                    index: route.route_index,
+                    direction: route.direction,
+                    channels: route.channel_count,
+                },
+            );
+            changed = previous.is_none_or(|r| r.index != route.route_index);
+            card.active_routes.values().filter(|r| r.direction == route.direction).count() == 1
+        }
+        None => true,
+    };
 
     for node in s.nodes.values_mut() {
         if node.device_id != Some(device_id) {
@@ -269,19 +715,38 @@ fn update_node_from_route(device_id: u32, route: &spa::ParsedRoute, state: &Arc<
             continue;
         }
 
+        // Cards with several outputs (or inputs) expose one node per route device;
+        // only the node bound to this route's device may take its values.
+        if node.profile_device.map_or(!sole_route, |d| d != route.route_device) {
+            continue;
+        }
+
+        let shown = shown_levels(node);
         node.route_index = Some(route.route_index);
         node.route_device = Some(route.route_device);
 
-        if let Some(v) = route.volume {
-            node.volume = v.cbrt();
+        let before = (node.volume, node.muted);
+        let stale = route.volume.is_some_and(|v| is_stale_echo(node, curve.to_slider(v)));
+        if let Some(v) = route.volume.filter(|_| !stale) {
+            node.volume = curve.to_slider(v);
         }
         if let Some(m) = route.muted {
-            node.muted = m;
+            apply_reported_mute(node, m);
         }
         if let Some(c) = route.channel_count {
-            node.channel_count = c;
+            node.channel_count = Some(c);
+        }
+        if !stale && !route.channel_volumes.is_empty() {
+            node.channel_volumes = route.channel_volumes.iter().map(|&v| curve.to_slider(v)).collect();
+            sync_channel_mutes(node);
+            if let Some(balance) = balance_of(&node.channel_volumes) {
+                node.balance = balance;
+            }
         }
+        mark_changed(node, before);
+        changed |= shown_levels(node) != shown;
     }
+    changed
 }
 
 // --- Metadata Handling ---
@@ -291,25 +756,36 @@ fn handle_metadata(
     props: &pw::spa::utils::dict::DictRef,
     registry: &pw::registry::RegistryRc,
     state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
+    repaint: &Repaint,
     metadata: &MetadataMap,
+    defaults_dirty: &DirtyFlag,
 ) {
     let name = props.get("metadata.name").unwrap_or("");
-    if name != "default" {
+    if name != "default" && name != "settings" {
         return;
     }
 
     let id = global.id;
-    let proxy: pw::metadata::Metadata = registry.bind(global).expect("Failed to bind metadata");
+    let proxy: pw::metadata::Metadata = match registry.bind(global) {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            report_error(state, repaint, format!("Failed to bind the {} metadata: {}", name, e));
+            return;
+        }
+    };
 
     let state_clone = state.clone();
-    let repaint_clone = repaint.clone();
+    let repaint = repaint.clone();
+    let defaults_dirty = defaults_dirty.clone();
+    let is_settings = name == "settings";
 
     let listener = proxy
         .add_listener_local()
         .property(move |subject, key, _type, value| {
-            if let Some(key) = key {
-                on_metadata_property(subject, key, value, &state_clone, &repaint_clone);
+            match key {
+                Some(key) if is_settings => on_settings_property(subject, key, value, &state_clone, &repaint),
+                Some(key) => on_metadata_property(subject, key, value, &state_clone, &defaults_dirty),
+                None => {}
             }
             0
         })
@@ -318,50 +794,139 @@ fn handle_metadata(
     metadata.borrow_mut().insert(
         id,
         MetadataWrapper {
-            proxy,
             _listener: Box::new(listener),
+            proxy,
+            name: name.to_string(),
         },
     );
 }
 
 fn on_metadata_property(
-    _subject: u32,
+    subject: u32,
     key: &str,
     value: Option<&str>,
     state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
+    defaults_dirty: &DirtyFlag,
 ) {
-    if key != "default.audio.sink" && key != "default.audio.source" {
+    if matches!(key, "target.object" | "target.node") {
+        // We clear `target.node` while setting `target.object`; only the latter decides.
+        if key == "target.object" || value.is_some() {
+            on_stream_target(subject, value, state);
+        }
         return;
     }
 
-    let node_name = value.and_then(|v| {
-        if v.starts_with('{') {
-            // Simple JSON parsing for {"name": "..."}
-            v.split("\"name\":\"")
-                .nth(1)
-                .and_then(|s| s.split('\"').next())
-        } else {
-            Some(v)
-        }
-    });
+    if !matches!(
+        key,
+        "default.audio.sink" | "default.audio.source" | "default.configured.audio.sink" | "default.configured.audio.source"
+    ) {
+        return;
+    }
+
+    let node_name = value.and_then(metadata_node_name);
 
     let mut s = state.lock();
-    let is_sink = key == "default.audio.sink";
+    let slot = match key {
+        "default.audio.sink" => &mut s.default_sink_name,
+        "default.audio.source" => &mut s.default_source_name,
+        "default.configured.audio.sink" => &mut s.configured_sink_name,
+        _ => &mut s.configured_source_name,
+    };
+    *slot = node_name;
 
-    if is_sink {
-        s.default_sink_name = node_name.map(|n| n.to_string());
-    } else {
-        s.default_source_name = node_name.map(|n| n.to_string());
+    defaults_dirty.set(true);
+}
+
+/// Track the graph clock overrides in the "settings" metadata; 0 or no value means not forced.
+fn on_settings_property(
+    subject: u32,
+    key: &str,
+    value: Option<&str>,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+) {
+    if subject != pw::core::PW_ID_CORE {
+        return;
     }
+    let forced = value.and_then(|v| v.trim().parse::<u32>().ok()).filter(|&v| v > 0);
 
-    for node in s.nodes.values_mut() {
-        if node.is_sink == is_sink {
-            node.is_default = Some(node.name.as_str()) == node_name;
+    {
+        let mut s = state.lock();
+        match key {
+            "clock.force-rate" => s.forced_rate = forced,
+            "clock.force-quantum" => s.forced_quantum = forced,
+            _ => return,
+        }
+    }
+
+    repaint.changed();
+}
+
+/// The `{"name": "..."}` object WirePlumber stores for default devices.
+#[derive(Deserialize)]
+struct MetadataTarget {
+    name: String,
+}
+
+/// The node name in a default-device metadata value: a JSON object with a `name`,
+/// or a bare node name as older session managers wrote it.
+fn metadata_node_name(value: &str) -> Option<String> {
+    if !value.trim_start().starts_with('{') {
+        return Some(value.to_string());
+    }
+
+    match serde_json::from_str::<MetadataTarget>(value) {
+        Ok(target) => Some(target.name),
+        Err(e) => {
+            log::warn!("Ignoring unreadable default device metadata {}: {}", value, e);
+            None
         }
     }
+}
+
+/// Keep a stream's `target_id` in line with retargets made through metadata, by us or others.
+/// The value is a node name or id (or serial, which we can't resolve and leave alone).
+fn on_stream_target(stream_id: u32, value: Option<&str>, state: &Arc<Mutex<AppState>>) {
+    let mut s = state.lock();
+    let target_id = match value {
+        None => None,
+        Some(v) => match v.parse::<u32>() {
+            Ok(id) if s.nodes.contains_key(&id) => Some(id),
+            Ok(_) => return,
+            Err(_) => match s.find_node_by_name(v) {
+                Some(node) => Some(node.id),
+                None => return,
+            },
+        },
+    };
+    if let Some(stream) = s.nodes.get_mut(&stream_id).filter(|n| n.is_stream) {
+        stream.target_id = target_id;
+    }
+}
 
-    request_repaint(repaint);
+/// Recompute `is_default` on every node from the current default names.
+fn refresh_defaults(state: &Arc<Mutex<AppState>>) {
+    let mut s = state.lock();
+    let s = &mut *s;
+
+    // Only a switch away from an existing default is worth highlighting, not the
+    // initial assignment at startup.
+    let had_default_sink = s.nodes.values().any(|n| n.is_default && n.is_sink);
+    let had_default_source = s.nodes.values().any(|n| n.is_default && !n.is_sink);
+
+    for node in s.nodes.values_mut() {
+        let (default_name, had_default) = if node.is_sink {
+            (s.default_sink_name.as_deref(), had_default_sink)
+        } else {
+            (s.default_source_name.as_deref(), had_default_source)
+        };
+
+        let was_default = node.is_default;
+        node.is_default = Some(node.name.as_str()) == default_name;
+        if node.is_default && !was_default && had_default {
+            node.default_since = Some(Instant::now());
+        }
+    }
 }
 
 // --- Node Handling ---
@@ -371,7 +936,7 @@ fn handle_node(
     props: &pw::spa::utils::dict::DictRef,
     registry: &pw::registry::RegistryRc,
     state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
+    repaint: &Repaint,
     nodes: &NodeMap,
 ) {
     let media_class = props.get("media.class").unwrap_or("");
@@ -384,6 +949,11 @@ fn handle_node(
         return;
     }
 
+    // Streams Copper opens itself (e.g. the volume feedback sound) are not for the user to control.
+    if props.get("application.process.id") == Some(std::process::id().to_string().as_str()) {
+        return;
+    }
+
     let id = global.id;
     let name = props.get("node.name").unwrap_or("Unknown").to_string();
     let mut description = props.get("node.description").unwrap_or(&name).to_string();
@@ -396,10 +966,27 @@ fn handle_node(
         }
     }
 
+    let stable_id = stable_id(props, &name, is_playback || is_recording);
     let device_id = props.get("device.id").and_then(|s| s.parse::<u32>().ok());
+    let profile_device = props.get("card.profile.device").and_then(|s| s.parse::<u32>().ok());
+    let kind = node_kind(props, is_sink || is_source, is_sink);
+    let is_monitor = is_source && (name.ends_with(".monitor") || props.get("stream.monitor") == Some("true"));
+
+    // Bound before the node is listed, so a node that shows up can also be controlled.
+    let node: pw::node::Node = match registry.bind(global) {
+        Ok(node) => node,
+        Err(e) => {
+            report_error(state, repaint, format!("Failed to bind {}: {}", name, e));
+            return;
+        }
+    };
 
     {
         let mut s = state.lock();
+        // Routes are usually reported before the card's nodes appear.
+        let active_route = device_id
+            .zip(profile_device)
+            .and_then(|(card, device)| s.cards.get(&card)?.active_routes.get(&device).copied());
         let is_default = if is_sink {
             s.default_sink_name.as_ref() == Some(&name)
         } else if is_source {
@@ -413,186 +1000,1419 @@ fn handle_node(
             AudioNode {
                 id,
                 name,
+                stable_id,
                 description,
                 volume: 1.0,
+                pre_mute_volume: None,
                 muted: false,
                 is_sink: is_sink || is_playback,
                 is_stream: is_playback || is_recording,
                 is_default,
+                default_since: None,
                 media_class: media_class.to_string(),
-                channel_count: 2,
+                app_name: props.get("application.name").map(str::to_string),
+                role: props.get("media.role").map(str::to_string),
+                kind,
+                is_monitor,
+                channel_count: props.get("audio.channels").and_then(|s| s.parse::<u32>().ok()),
+                channel_volumes: Vec::new(),
+                channel_muted: Vec::new(),
+                channel_unmute_levels: Vec::new(),
+                balance: 0.0,
                 device_id,
+                profile_device,
                 target_id: props
                     .get("target.node")
                     .or_else(|| props.get("node.target"))
                     .and_then(|s| s.parse::<u32>().ok()),
-                route_index: None,
-                route_device: None,
+                route_index: active_route.map(|r| r.index),
+                route_device: active_route.and(profile_device),
+                volume_request: None,
+                sent_volumes: Vec::new(),
+                volume_overridden: false,
+                meter_available: false,
+                peak: 0.0,
+                peak_hold: 0.0,
+                error_count: 0,
+                error: None,
+                error_at: None,
+                last_changed: None,
+                volume_known: false,
+                mute_sent_at: None,
+                ignores_mute: false,
+                zero_volume_mute: false,
+                format: None,
+                latency: None,
+                state: NodeState::Idle,
             },
         );
     }
 
-    request_repaint(repaint);
-
-    let node: pw::node::Node = registry.bind(global).expect("Failed to bind node");
+    repaint.changed();
 
     let state_clone = state.clone();
     let repaint_clone = repaint.clone();
+    let state_info = state.clone();
+    let repaint_info = repaint.clone();
 
     let listener = node
         .add_listener_local()
-        .param(move |_seq, _id, _index, _next, param| {
-            on_node_param(id, param, &state_clone, &repaint_clone);
+        .info(move |info| {
+            on_node_info(id, info, &state_info, &repaint_info);
+        })
+        .param(move |_seq, param_id, _index, _next, param| {
+            if param_id == spa_lib::param::ParamType::Format {
+                on_node_format(id, param, &state_clone, &repaint_clone);
+            } else {
+                on_node_param(id, param, &state_clone, &repaint_clone);
+            }
         })
         .register();
 
-    node.subscribe_params(&[spa_lib::param::ParamType::Props]);
+    node.subscribe_params(&[spa_lib::param::ParamType::Props, spa_lib::param::ParamType::Format]);
 
     nodes.borrow_mut().insert(
         id,
         NodeWrapper {
-            proxy: node,
             _listener: Box::new(listener),
+            proxy: node,
         },
     );
 }
 
-fn on_node_param(
-    node_id: u32,
-    param: Option<&spa_lib::pod::Pod>,
-    state: &Arc<Mutex<AppState>>,
-    repaint: &Arc<Mutex<Option<egui::Context>>>,
-) {
-    let Some(param) = param else { return };
-    let props = unsafe { spa::parse_props(param.as_raw_ptr() as *mut _) };
+/// A key for the node that survives restarts. A device's `node.name` already is one, built
+/// from the card's hardware path and profile; a stream's is only unique within its
+/// application, so the application's binary (or name) goes in front of it.
+fn stable_id(props: &pw::spa::utils::dict::DictRef, name: &str, is_stream: bool) -> String {
+    if !is_stream {
+        return name.to_string();
+    }
+    let app = props
+        .get("application.process.binary")
+        .or_else(|| props.get("application.name"))
+        .unwrap_or("unknown");
+    format!("{}:{}", app, name)
+}
 
-    if props.volume.is_none() && props.muted.is_none() && props.channel_count.is_none() {
-        return;
+/// Guess what a node is from its props: form factor and profile for devices, role and
+/// application for streams.
+fn node_kind(props: &pw::spa::utils::dict::DictRef, is_device: bool, is_sink: bool) -> NodeKind {
+    let prop = |key| props.get(key).unwrap_or("").to_lowercase();
+
+    if is_device {
+        let form_factor = prop("device.form_factor");
+        let hints = [form_factor.as_str(), &prop("device.icon_name"), &prop("device.profile.name")].join(" ");
+        return if hints.contains("headphone") || hints.contains("headset") || hints.contains("hands-free") {
+            if is_sink { NodeKind::Headphones } else { NodeKind::Microphone }
+        } else if !is_sink {
+            NodeKind::Microphone
+        } else if hints.contains("hdmi") || hints.contains("iec958") || matches!(form_factor.as_str(), "tv" | "monitor") {
+            NodeKind::Display
+        } else {
+            NodeKind::Speakers
+        };
     }
 
-    {
-        let mut s = state.lock();
-        if let Some(node) = s.nodes.get_mut(&node_id) {
-            if let Some(v) = props.volume {
-                node.volume = v.cbrt();
-            }
-            if let Some(m) = props.muted {
-                node.muted = m;
-            }
-            if let Some(c) = props.channel_count {
-                node.channel_count = c;
-            }
-        }
+    match prop("media.role").as_str() {
+        "music" => return NodeKind::Music,
+        "movie" | "video" => return NodeKind::Video,
+        "game" => return NodeKind::Game,
+        "communication" | "phone" => return NodeKind::Call,
+        "notification" | "event" => return NodeKind::Notification,
+        _ => {}
     }
 
-    request_repaint(repaint);
+    let app = [prop("application.process.binary"), prop("application.name")].join(" ");
+    let mentions = |names: &[&str]| names.iter().any(|n| app.contains(n));
+    if mentions(&["firefox", "chrom", "brave", "vivaldi", "opera", "epiphany", "librewolf"]) {
+        NodeKind::Browser
+    } else if mentions(&["spotify", "rhythmbox", "lollypop", "amberol", "elisa", "clementine", "strawberry", "mpd"]) {
+        NodeKind::Music
+    } else if mentions(&["mpv", "vlc", "totem", "celluloid", "kodi"]) {
+        NodeKind::Video
+    } else if mentions(&["discord", "zoom", "teams", "skype", "slack", "telegram", "signal", "webex"]) {
+        NodeKind::Call
+    } else if mentions(&["steam", "wine", "lutris"]) {
+        NodeKind::Game
+    } else {
+        NodeKind::App
+    }
 }
 
-// --- Command Processing ---
+/// Count transitions into the error state, and track the latency the node asks for.
+/// PipeWire only reports xruns through the profiler, so node errors are the closest
+/// per-node glitch signal in the node info.
+fn on_node_info(
+    id: u32,
+    info: &pw::node::NodeInfoRef,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+) {
+    let (run_state, error) = match info.state() {
+        pw::node::NodeState::Error(message) => (NodeState::Error, Some(message.to_string())),
+        pw::node::NodeState::Running => (NodeState::Running, None),
+        pw::node::NodeState::Suspended => (NodeState::Suspended, None),
+        pw::node::NodeState::Idle | pw::node::NodeState::Creating => (NodeState::Idle, None),
+    };
+    let latency = info.props().and_then(|props| props.get("node.latency")).and_then(parse_latency);
 
-fn process_commands(rx: &Receiver<PwCommand>, state: &Arc<Mutex<AppState>>, nodes: &NodeMap, devices: &DeviceMap, metadata: &MetadataMap) {
-    while let Ok(cmd) = rx.try_recv() {
-        match cmd {
-            PwCommand::Quit => std::process::exit(0),
-            PwCommand::SetVolume(node_id, vol) => set_volume(node_id, vol, state, nodes, devices),
-            PwCommand::SetMute(node_id, mute) => set_mute(node_id, mute, state, nodes, devices),
-            PwCommand::SetDefault(node_id) => set_default(node_id, state, metadata),
-            PwCommand::SetCardProfile(card_id, profile_index) => set_card_profile(card_id, profile_index, devices),
-        }
+    let mut s = state.lock();
+    let Some(node) = s.nodes.get_mut(&id) else { return };
+    if node.error == error && node.latency == latency && node.state == run_state {
+        return;
     }
-}
-
-fn set_card_profile(card_id: u32, profile_index: u32, devices: &DeviceMap) {
-    let devices = devices.borrow();
-    let Some(device) = devices.get(&card_id) else { return };
+    node.state = run_state;
 
-    if let Some(pod) = spa::build_profile_pod(profile_index) {
-        device.proxy.set_param(spa_lib::param::ParamType::Profile, 0, unsafe {
-            pipewire::spa::pod::Pod::from_raw(pod.as_ptr() as *const _)
-        });
+    if error.is_some() && node.error.is_none() {
+        node.error_count += 1;
+        node.error_at = Some(Instant::now());
     }
+    node.error = error;
+    node.latency = latency;
+    drop(s);
+
+    repaint.changed();
 }
 
-fn set_default(node_id: u32, state: &Arc<Mutex<AppState>>, metadata: &MetadataMap) {
-    let (name, is_sink) = {
-        let s = state.lock();
-        let Some(node) = s.nodes.get(&node_id) else { return };
-        (node.name.clone(), node.is_sink)
-    };
+/// Parse a `node.latency` value such as "1024/48000".
+fn parse_latency(value: &str) -> Option<NodeLatency> {
+    let (quantum, rate) = value.split_once('/')?;
+    let quantum = quantum.trim().parse().ok().filter(|&q| q > 0)?;
+    let rate = rate.trim().parse().ok().filter(|&r| r > 0)?;
+    Some(NodeLatency { quantum, rate })
+}
 
-    let metadata = metadata.borrow();
-    let Some(wrapper) = metadata.values().next() else { return };
+/// Give a new stream the configured volume for its `media.role`, if any.
+fn apply_role_volume(
+    stream_id: u32,
+    props: &pw::spa::utils::dict::DictRef,
+    state: &Arc<Mutex<AppState>>,
+    nodes: &NodeMap,
+    devices: &DeviceMap,
+) {
+    let Some(role) = props.get("media.role") else { return };
 
-    let key = if is_sink {
-        "default.audio.sink"
-    } else {
-        "default.audio.source"
+    let volume = {
+        let s = state.lock();
+        if !s.nodes.get(&stream_id).is_some_and(|n| n.is_stream) {
+            return;
+        }
+        let Some(&volume) = s.role_volumes.get(&role.to_lowercase()) else { return };
+        volume
     };
 
-    let value = format!("{{\"name\": \"{}\"}}", name);
-    wrapper.proxy.set_property(0, key, Some("Spa:String:JSON"), Some(&value));
+    set_volume(stream_id, volume, state, nodes, devices);
 }
 
-fn set_volume(node_id: u32, vol: f32, state: &Arc<Mutex<AppState>>, nodes: &NodeMap, devices: &DeviceMap) {
-    let (is_stream, channel_count, device_id, route_index, route_device) = {
+/// Give a new playback stream the volume and mute last set for its application. This only
+/// runs when the stream appears, so later changes by the session manager are left alone.
+fn restore_app_volume(stream_id: u32, state: &Arc<Mutex<AppState>>, nodes: &NodeMap, devices: &DeviceMap) {
+    let (volume, muted) = {
         let s = state.lock();
-        let Some(node) = s.nodes.get(&node_id) else { return };
-        (node.is_stream, node.channel_count, node.device_id, node.route_index, node.route_device)
+        let Some(stream) = s.nodes.get(&stream_id).filter(|n| n.is_stream && n.is_sink) else { return };
+        let Some(&saved) = stream.app_name.as_ref().and_then(|app| s.app_volumes.get(app)) else { return };
+        saved
     };
 
-    if is_stream {
-        let nodes = nodes.borrow();
-        let Some(wrapper) = nodes.get(&node_id) else { return };
-        if let Some(buf) = spa::build_props_volume_pod(channel_count, vol, None) {
-            if let Some(pod) = spa_lib::pod::Pod::from_bytes(&buf) {
-                wrapper.proxy.set_param(spa_lib::param::ParamType::Props, 0, pod);
-            }
-        }
-    } else {
-        let (Some(device_id), Some(route_index), Some(route_device)) = (device_id, route_index, route_device) else { return };
-        let devices = devices.borrow();
-        let Some(wrapper) = devices.get(&device_id) else { return };
-
-        if let Some(buf) = spa::build_route_volume_pod(route_index, route_device, channel_count, vol, None) {
-            if let Some(pod) = spa_lib::pod::Pod::from_bytes(&buf) {
-                wrapper.proxy.set_param(spa_lib::param::ParamType::Route, 0, pod);
-            }
-        }
+    set_volume(stream_id, volume, state, nodes, devices);
+    if muted {
+        set_mute(stream_id, true, state, nodes, devices);
     }
 }
 
-fn set_mute(node_id: u32, mute: bool, state: &Arc<Mutex<AppState>>, nodes: &NodeMap, devices: &DeviceMap) {
-    let (is_stream, channel_count, volume, device_id, route_index, route_device) = {
-        let s = state.lock();
-        let Some(node) = s.nodes.get(&node_id) else { return };
-        (node.is_stream, node.channel_count, node.volume, node.device_id, node.route_index, node.route_device)
-    };
-
-    if is_stream {
-        let nodes = nodes.borrow();
-        let Some(wrapper) = nodes.get(&node_id) else { return };
-        if let Some(buf) = spa::build_props_volume_pod(channel_count, volume, Some(mute)) {
-            if let Some(pod) = spa_lib::pod::Pod::from_bytes(&buf) {
-                wrapper.proxy.set_param(spa_lib::param::ParamType::Props, 0, pod);
-            }
-        }
-    } else {
-        let (Some(device_id), Some(route_index), Some(route_device)) = (device_id, route_index, route_device) else { return };
-        let devices = devices.borrow();
-        let Some(wrapper) = devices.get(&device_id) else { return };
+/// Record a volume or mute change made from Copper as its application's preference.
+fn remember_app_volume(
+    node_id: u32,
+    volume: Option<f32>,
+    mute: Option<bool>,
+    state: &Arc<Mutex<AppState>>,
+    changed: &PendingSave,
+) {
+    let mut s = state.lock();
+    let Some(stream) = s.nodes.get(&node_id).filter(|n| n.is_stream && n.is_sink) else { return };
+    let Some(app) = stream.app_name.clone() else { return };
+    let current = (stream.pre_mute_volume.unwrap_or(stream.volume), stream.muted);
 
-        if let Some(buf) = spa::build_route_volume_pod(route_index, route_device, channel_count, volume, Some(mute)) {
-            if let Some(pod) = spa_lib::pod::Pod::from_bytes(&buf) {
-                wrapper.proxy.set_param(spa_lib::param::ParamType::Route, 0, pod);
-            }
-        }
+    let entry = s.app_volumes.entry(app).or_insert(current);
+    if let Some(volume) = volume {
+        entry.0 = volume;
+    }
+    if let Some(mute) = mute {
+        entry.1 = mute;
     }
+    changed.set(Some(Instant::now()));
 }
 
-// --- Helpers ---
+fn save_app_volumes(state: &Arc<Mutex<AppState>>, changed: &PendingSave) {
+    changed.set(None);
+    let volumes = state.lock().app_volumes.clone();
+    crate::config::save_app_volumes(&volumes);
+}
 
-fn request_repaint(repaint: &Arc<Mutex<Option<egui::Context>>>) {
-    if let Some(ctx) = repaint.lock().as_ref() {
+/// Unmute the default source when a call app opens a recording stream, if enabled.
+fn unmute_source_for_call(
+    stream_id: u32,
+    props: &pw::spa::utils::dict::DictRef,
+    state: &Arc<Mutex<AppState>>,
+    nodes: &NodeMap,
+    devices: &DeviceMap,
+) {
+    let source_id = {
+        let s = state.lock();
+        if !s.auto_unmute_on_call {
+            return;
+        }
+
+        let Some(stream) = s.nodes.get(&stream_id) else { return };
+        if !stream.is_stream || stream.is_sink {
+            return;
+        }
+
+        let is_communication = props
+            .get("media.role")
+            .is_some_and(|role| role.eq_ignore_ascii_case("communication"));
+        let app_name = props.get("application.name").unwrap_or("").to_lowercase();
+        let is_call_app = s
+            .call_apps
+            .iter()
+            .any(|app| !app.is_empty() && app_name.contains(&app.to_lowercase()));
+        if !is_communication && !is_call_app {
+            return;
+        }
+
+        let Some(source) = s.find_default_source().filter(|n| !n.is_sink) else { return };
+        if !source.muted {
+            return;
+        }
+        source.id
+    };
+
+    log::info!("Call started, unmuting default source");
+    set_mute(source_id, false, state, nodes, devices);
+}
+
+fn on_node_param(
+    node_id: u32,
+    param: Option<&spa_lib::pod::Pod>,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+) {
+    let Some(param) = param else { return };
+    let props = unsafe { spa::parse_props(param.as_raw_ptr() as *mut _) };
+
+    if props.volume.is_none() && props.muted.is_none() && props.channel_count.is_none() {
+        return;
+    }
+
+    let changed = {
+        let mut s = state.lock();
+        let curve = s.volume_curve;
+        let Some(node) = s.nodes.get_mut(&node_id) else { return };
+        let shown = shown_levels(node);
+        let before = (node.volume, node.muted);
+        let stale = props.volume.is_some_and(|v| is_stale_echo(node, curve.to_slider(v)));
+        if let Some(v) = props.volume.filter(|_| !stale) {
+            node.volume = curve.to_slider(v);
+            check_volume_override(node);
+        }
+        if let Some(m) = props.muted {
+            apply_reported_mute(node, m);
+        }
+        if let Some(c) = props.channel_count {
+            node.channel_count = Some(c);
+        }
+        if !stale && !props.channel_volumes.is_empty() {
+            node.channel_volumes = props.channel_volumes.iter().map(|&v| curve.to_slider(v)).collect();
+            sync_channel_mutes(node);
+            if let Some(balance) = balance_of(&node.channel_volumes) {
+                node.balance = balance;
+            }
+        }
+        mark_changed(node, before);
+        shown_levels(node) != shown
+    };
+
+    if changed {
+        repaint.changed();
+    }
+}
+
+/// What the UI shows of a node's levels, to tell whether a param update changed anything.
+fn shown_levels(node: &AudioNode) -> (f32, bool, Vec<f32>, Vec<bool>, f32, Option<u32>, bool) {
+    (
+        node.volume,
+        node.muted,
+        node.channel_volumes.clone(),
+        node.channel_muted.clone(),
+        node.balance,
+        node.channel_count,
+        node.volume_overridden,
+    )
+}
+
+/// Record the node's negotiated format. A missing or unfixed Format clears it, which hides
+/// the format line until the node runs again.
+fn on_node_format(
+    node_id: u32,
+    param: Option<&spa_lib::pod::Pod>,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+) {
+    let format = param.and_then(|p| unsafe { spa::parse_format(p.as_raw_ptr()) }).map(|f| AudioFormat {
+        rate: f.rate,
+        channels: f.channels,
+        sample_format: spa::sample_format_name(f.format),
+    });
+
+    {
+        let mut s = state.lock();
+        let Some(node) = s.nodes.get_mut(&node_id) else { return };
+        if node.format == format {
+            return;
+        }
+        node.format = format;
+    }
+
+    repaint.changed();
+}
+
+/// Take a reported mute state, keeping nodes muted via zeroed volumes shown as muted.
+/// An unmute we didn't ask for (another mixer, a keyboard key) drops the level saved at
+/// mute time, so the next mute saves the current one instead.
+fn apply_reported_mute(node: &mut AudioNode, muted: bool) {
+    node.muted = muted || node.zero_volume_mute;
+    if muted {
+        node.mute_sent_at = None;
+    } else if node.mute_sent_at.is_none() && !node.zero_volume_mute {
+        node.pre_mute_volume = None;
+    }
+}
+
+/// Fall back to zeroing channel volumes on devices that didn't report a requested mute in time.
+fn check_ignored_mutes(state: &Arc<Mutex<AppState>>, nodes: &NodeMap, devices: &DeviceMap) {
+    let ignored: Vec<u32> = {
+        let mut s = state.lock();
+        s.nodes
+            .values_mut()
+            .filter(|node| node.mute_sent_at.is_some_and(|at| at.elapsed() > MUTE_CONFIRM_TIMEOUT))
+            .map(|node| {
+                node.mute_sent_at = None;
+                log::warn!("{} ignored a mute request, muting by zeroing its volume", node.description);
+                node.ignores_mute = true;
+                node.id
+            })
+            .collect()
+    };
+
+    for node_id in ignored {
+        set_mute(node_id, true, state, nodes, devices);
+    }
+}
+
+/// Record when a node's volume or mute state moved away from `before`.
+fn mark_changed(node: &mut AudioNode, before: (f32, bool)) {
+    let changed = (node.volume - before.0).abs() > VOLUME_EPSILON || node.muted != before.1;
+    if changed && node.volume_known {
+        node.last_changed = Some(Instant::now());
+    }
+    node.volume_known = true;
+}
+
+/// Show a volume as soon as we send it, rather than after PipeWire reports it back.
+fn apply_sent_volume(node: &mut AudioNode, volume: f32, levels: &[f32]) {
+    let before = (node.volume, node.muted);
+    node.volume = volume;
+    node.channel_volumes = levels.to_vec();
+    sync_channel_mutes(node);
+    if let Some(balance) = balance_of(levels) {
+        node.balance = balance;
+    }
+    node.sent_volumes.retain(|(_, at)| at.elapsed() < VOLUME_IN_FLIGHT);
+    node.sent_volumes.push((volume, Instant::now()));
+    mark_changed(node, before);
+}
+
+/// Keep the channel mutes in step with the channel count, and drop the mute of any
+/// channel that has been given a level again, here or elsewhere.
+fn sync_channel_mutes(node: &mut AudioNode) {
+    let count = node.channel_volumes.len();
+    node.channel_muted.resize(count, false);
+    node.channel_unmute_levels.resize(count, 0.0);
+    for (muted, level) in node.channel_muted.iter_mut().zip(&node.channel_volumes) {
+        if *level > VOLUME_EPSILON {
+            *muted = false;
+        }
+    }
+}
+
+/// Whether a reported volume is the echo of an earlier write that a newer one replaced.
+/// Anything else, including a value the device clamped or an app set, is taken as is.
+fn is_stale_echo(node: &mut AudioNode, reported: f32) -> bool {
+    // Reports come in order, so once the newest value is back no older echo is left.
+    if (reported - node.volume).abs() <= VOLUME_EPSILON {
+        node.sent_volumes.clear();
+        return false;
+    }
+    node.sent_volumes.retain(|(_, at)| at.elapsed() < VOLUME_IN_FLIGHT);
+    node.sent_volumes.iter().any(|(v, _)| (reported - v).abs() <= VOLUME_EPSILON)
+}
+
+/// Detect an application resetting a stream volume right after we changed it.
+///
+/// Our own value is expected to echo back first; a different value arriving after
+/// that echo, within the revert window, means the app overrode us.
+fn check_volume_override(node: &mut AudioNode) {
+    let Some(request) = node.volume_request.as_mut() else { return };
+
+    if request.sent_at.elapsed() > VOLUME_REVERT_WINDOW {
+        node.volume_request = None;
+    } else if (node.volume - request.volume).abs() <= VOLUME_EPSILON {
+        request.confirmed = true;
+    } else if request.confirmed {
+        log::warn!("{} reset its stream volume after it was changed", node.description);
+        node.volume_overridden = true;
+        node.volume_request = None;
+    }
+}
+
+// --- Command Processing ---
+
+/// Apply the queued commands. Returns how the session should end: right away for Quit,
+/// dropping the rest of the batch, or after the batch for Rescan.
+#[allow(clippy::too_many_arguments)]
+fn process_commands(
+    rx: &Receiver<PwCommand>,
+    core: &pw::core::CoreRc,
+    registry: &pw::registry::RegistryRc,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+    nodes: &NodeMap,
+    devices: &DeviceMap,
+    metadata: &MetadataMap,
+    feedback: &feedback::Feedback,
+    notifier: &notify::Notifier,
+    app_volumes_changed: &PendingSave,
+) -> Option<SessionEnd> {
+    // Drain everything queued since the last tick so sweeping actions are applied in one pass.
+    let commands: Vec<PwCommand> = rx.try_iter().collect();
+    let mut end = None;
+
+    for cmd in dedupe_commands(commands) {
+        match cmd {
+            PwCommand::Quit => return Some(SessionEnd::Quit),
+            PwCommand::Rescan => end = Some(SessionEnd::Rescan),
+            PwCommand::SetVolume(node_id, vol) => {
+                let linked = linked_volumes(node_id, vol, state);
+                set_volume(node_id, vol, state, nodes, devices);
+                for (member_id, member_vol) in linked {
+                    set_volume(member_id, member_vol, state, nodes, devices);
+                }
+                feedback.volume_changed(node_id, state);
+                notifier.volume_changed(node_id, state);
+                remember_app_volume(node_id, Some(vol), None, state, app_volumes_changed);
+            }
+            PwCommand::SetMute(node_id, mute) => {
+                set_mute(node_id, mute, state, nodes, devices);
+                notifier.volume_changed(node_id, state);
+                remember_app_volume(node_id, None, Some(mute), state, app_volumes_changed);
+            }
+            PwCommand::SetChannelVolumes(node_id, levels) => {
+                set_channel_volumes(node_id, levels, state, nodes, devices);
+                feedback.volume_changed(node_id, state);
+            }
+            PwCommand::SetDefault(node_id) => set_default(node_id, state, metadata),
+            PwCommand::MoveStream(stream_id, target_id) => move_stream(stream_id, target_id, state, metadata),
+            PwCommand::SetCardProfile(card_id, profile_index) => set_card_profile(card_id, profile_index, devices),
+            PwCommand::SetChannelMutes(node_id, muted) => set_channel_mutes(node_id, muted, state, nodes, devices),
+            PwCommand::SetPort(node_id, route_index) => set_port(node_id, route_index, state, devices),
+            PwCommand::SetForcedRate(rate) => set_forced_clock("clock.force-rate", rate, metadata),
+            PwCommand::SetForcedQuantum(quantum) => set_forced_clock("clock.force-quantum", quantum, metadata),
+            PwCommand::ResetRouteVolumes(card_id) => reset_route_volumes(card_id, state, devices),
+            PwCommand::CreateLink(output_node, input_node) => create_link(output_node, input_node, state, repaint, core),
+            PwCommand::DestroyLink(link_id) => destroy_link(link_id, state, repaint, registry),
+        }
+    }
+
+    end
+}
+
+/// New volumes for the other members of a node's volume group as it moves to `volume`,
+/// each keeping its ratio to the node. From silence there is no ratio, so they all take
+/// `volume`. Members only go past 100% if the node itself does.
+fn linked_volumes(node_id: u32, volume: f32, state: &Arc<Mutex<AppState>>) -> Vec<(u32, f32)> {
+    let s = state.lock();
+    let Some(node) = s.nodes.get(&node_id) else { return Vec::new() };
+    let ratio = (node.volume > VOLUME_EPSILON).then(|| volume / node.volume);
+    let max = volume.max(1.0);
+    s.linked_nodes(node)
+        .into_iter()
+        .map(|member| (member.id, ratio.map_or(volume, |r| member.volume * r).clamp(0.0, max)))
+        .collect()
+}
+
+/// Keep only the last command of each kind per node (or card), preserving order.
+fn dedupe_commands(commands: Vec<PwCommand>) -> Vec<PwCommand> {
+    let mut seen = HashSet::new();
+    let mut kept: Vec<PwCommand> = commands
+        .into_iter()
+        .rev()
+        .filter(|cmd| command_key(cmd).is_none_or(|key| seen.insert(key)))
+        .collect();
+    kept.reverse();
+    kept
+}
+
+/// What a command overwrites; a later command with the same key makes it redundant.
+/// Both volume commands write every channel, so a slider drag and a channel or balance
+/// drag on the same node coalesce into whichever came last.
+fn command_key(cmd: &PwCommand) -> Option<(u8, u32)> {
+    match *cmd {
+        PwCommand::SetVolume(node_id, _) | PwCommand::SetChannelVolumes(node_id, _) => Some((0, node_id)),
+        PwCommand::SetMute(node_id, _) => Some((1, node_id)),
+        PwCommand::SetDefault(node_id) => Some((2, node_id)),
+        PwCommand::SetCardProfile(card_id, _) => Some((3, card_id)),
+        PwCommand::ResetRouteVolumes(card_id) => Some((4, card_id)),
+        PwCommand::MoveStream(stream_id, _) => Some((6, stream_id)),
+        PwCommand::SetPort(node_id, _) => Some((7, node_id)),
+        PwCommand::SetChannelMutes(node_id, _) => Some((10, node_id)),
+        PwCommand::SetForcedRate(_) => Some((8, 0)),
+        PwCommand::SetForcedQuantum(_) => Some((9, 0)),
+        // Each names a different link, so none is redundant.
+        PwCommand::CreateLink(..) | PwCommand::DestroyLink(_) | PwCommand::Quit | PwCommand::Rescan => None,
+    }
+}
+
+/// Link two nodes port by port. Ports pair up by channel; failing that, a single port on
+/// either side takes all ports of the other (mono up- or downmix), and otherwise they pair
+/// in order. Pairs that are already linked are skipped.
+fn create_link(
+    output_node: u32,
+    input_node: u32,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+    core: &pw::core::CoreRc,
+) {
+    let pairs: Vec<(u32, u32)> = {
+        let s = state.lock();
+        let mut outputs: Vec<(u32, &PortInfo)> = s
+            .ports
+            .iter()
+            .filter(|(_, p)| p.node_id == output_node && p.is_output)
+            .map(|(&id, p)| (id, p))
+            .collect();
+        let mut inputs: Vec<(u32, &PortInfo)> = s
+            .ports
+            .iter()
+            .filter(|(_, p)| p.node_id == input_node && !p.is_output)
+            .map(|(&id, p)| (id, p))
+            .collect();
+        outputs.sort_by_key(|(id, _)| *id);
+        inputs.sort_by_key(|(id, _)| *id);
+
+        let by_channel: Vec<(u32, u32)> = outputs
+            .iter()
+            .filter_map(|(out_id, out)| {
+                let channel = out.channel.as_ref()?;
+                let (in_id, _) = inputs.iter().find(|(_, input)| input.channel.as_ref() == Some(channel))?;
+                Some((*out_id, *in_id))
+            })
+            .collect();
+
+        let pairs = if !by_channel.is_empty() {
+            by_channel
+        } else if let [(out_id, _)] = outputs[..] {
+            inputs.iter().map(|(in_id, _)| (out_id, *in_id)).collect()
+        } else if let [(in_id, _)] = inputs[..] {
+            outputs.iter().map(|(out_id, _)| (*out_id, in_id)).collect()
+        } else {
+            outputs.iter().zip(&inputs).map(|((out_id, _), (in_id, _))| (*out_id, *in_id)).collect()
+        };
+
+        pairs
+            .into_iter()
+            .filter(|&(out_id, in_id)| {
+                !s.links.values().any(|l| l.output_port == out_id && l.input_port == in_id)
+            })
+            .collect()
+    };
+
+    if pairs.is_empty() {
+        let message = format!("Cannot link node {} to node {}: no matching unlinked ports", output_node, input_node);
+        report_error(state, repaint, message);
+        return;
+    }
+
+    for (output_port, input_port) in pairs {
+        let props = pw::properties::properties! {
+            "link.output.node" => output_node.to_string(),
+            "link.output.port" => output_port.to_string(),
+            "link.input.node" => input_node.to_string(),
+            "link.input.port" => input_port.to_string(),
+            // Keep the link once our proxy is gone, like `pw-link` does.
+            "object.linger" => "true",
+        };
+        if let Err(e) = core.create_object::<pw::link::Link>("link-factory", &props) {
+            report_error(state, repaint, format!("Failed to link port {} to port {}: {}", output_port, input_port, e));
+        }
+    }
+}
+
+fn destroy_link(
+    link_id: u32,
+    state: &Arc<Mutex<AppState>>,
+    repaint: &Repaint,
+    registry: &pw::registry::RegistryRc,
+) {
+    if let Err(e) = registry.destroy_global(link_id).into_result() {
+        report_error(state, repaint, format!("Failed to remove link {}: {}", link_id, e));
+    }
+}
+
+fn set_card_profile(card_id: u32, profile_index: u32, devices: &DeviceMap) {
+    let devices = devices.borrow();
+    let Some(device) = devices.get(&card_id) else { return };
+
+    if let Some(pod) = spa::build_profile_pod(profile_index) {
+        device.proxy.set_param(spa_lib::param::ParamType::Profile, 0, unsafe {
+            pipewire::spa::pod::Pod::from_raw(pod.as_ptr() as *const _)
+        });
+    }
+}
+
+/// Switch a device node to another route (port) of its card.
+fn set_port(node_id: u32, route_index: u32, state: &Arc<Mutex<AppState>>, devices: &DeviceMap) {
+    let (card_id, route_device) = {
+        let s = state.lock();
+        let Some(node) = s.nodes.get(&node_id) else { return };
+        // Without the route device the route can't be addressed; it comes with the first Route param.
+        let (Some(card_id), Some(route_device)) = (node.device_id, node.route_device.or(node.profile_device)) else {
+            log::warn!("Cannot change the port of {}: its route device is unknown", node.name);
+            return;
+        };
+        (card_id, route_device)
+    };
+
+    let devices = devices.borrow();
+    let Some(device) = devices.get(&card_id) else { return };
+
+    if let Some(pod) = spa::build_route_select_pod(route_index, route_device)
+        && let Some(pod) = spa_lib::pod::Pod::from_bytes(&pod)
+    {
+        device.proxy.set_param(spa_lib::param::ParamType::Route, 0, pod);
+    }
+}
+
+/// Put every active route of a card back to the session manager's default volume, leaving
+/// mute alone, and save it so that level replaces the one remembered. Then re-read the
+/// routes so the UI shows what is now in effect.
+fn reset_route_volumes(card_id: u32, state: &Arc<Mutex<AppState>>, devices: &DeviceMap) {
+    let (name, routes, curve) = {
+        let s = state.lock();
+        let Some(card) = s.cards.get(&card_id) else { return };
+        let routes: Vec<(u32, u32, u32, u32)> = card
+            .active_routes
+            .iter()
+            .filter_map(|(&device, route)| Some((route.index, device, route.direction, route.channels?)))
+            .collect();
+        (card.name.clone(), routes, s.volume_curve)
+    };
+
+    let devices = devices.borrow();
+    let Some(device) = devices.get(&card_id) else { return };
+
+    for (route_index, route_device, direction, channels) in routes {
+        let gain = if direction == 1 { DEFAULT_OUTPUT_GAIN } else { DEFAULT_INPUT_GAIN };
+        let levels = vec![curve.to_slider(gain); channels.max(1) as usize];
+        if let Some(buf) = spa::build_route_volume_pod(route_index, route_device, &levels, None, true, curve)
+            && let Some(pod) = spa_lib::pod::Pod::from_bytes(&buf)
+        {
+            device.proxy.set_param(spa_lib::param::ParamType::Route, 0, pod);
+        }
+    }
+    log::info!("Reset route volumes of {} to the defaults", name);
+
+    device.proxy.enum_params(0, Some(spa_lib::param::ParamType::Route), 0, u32::MAX);
+}
+
+fn set_default(node_id: u32, state: &Arc<Mutex<AppState>>, metadata: &MetadataMap) {
+    let (name, is_sink) = {
+        let s = state.lock();
+        let Some(node) = s.nodes.get(&node_id) else { return };
+        (node.name.clone(), node.is_sink)
+    };
+
+    let metadata = metadata.borrow();
+    let Some(wrapper) = named_metadata(&metadata, "default") else { return };
+
+    let key = if is_sink {
+        "default.audio.sink"
+    } else {
+        "default.audio.source"
+    };
+
+    let value = serde_json::json!({ "name": name }).to_string();
+    wrapper.proxy.set_property(0, key, Some("Spa:String:JSON"), Some(&value));
+}
+
+/// Point a stream at another device through the `target.object` metadata the session
+/// manager follows, or clear it so the stream goes back to following the default.
+/// Playback streams can only go to sinks; recording streams to sources or a sink's monitor.
+fn move_stream(stream_id: u32, target_id: Option<u32>, state: &Arc<Mutex<AppState>>, metadata: &MetadataMap) {
+    let target_name = {
+        let mut s = state.lock();
+        let Some(is_playback) = s.nodes.get(&stream_id).filter(|n| n.is_stream).map(|n| n.is_sink) else { return };
+        let target_name = match target_id {
+            Some(id) => {
+                let Some(target) = s.nodes.get(&id).filter(|n| !n.is_stream) else { return };
+                if is_playback && !target.is_sink {
+                    log::warn!("Not moving playback stream {} to {}, which is not an output", stream_id, target.name);
+                    return;
+                }
+                Some(target.name.clone())
+            }
+            None => None,
+        };
+        if let Some(stream) = s.nodes.get_mut(&stream_id) {
+            stream.target_id = target_id;
+        }
+        target_name
+    };
+
+    let metadata = metadata.borrow();
+    let Some(wrapper) = named_metadata(&metadata, "default") else {
+        log::warn!("Cannot move stream {}: no default metadata object", stream_id);
+        return;
+    };
+    // `target.node` is the older key; clear it so it can't pin the stream elsewhere.
+    wrapper.proxy.set_property(stream_id, "target.node", None, None);
+    wrapper.proxy.set_property(stream_id, "target.object", None, target_name.as_deref());
+}
+
+/// Force the whole graph to a sample rate or quantum through the "settings" metadata,
+/// or let it choose again with 0. PipeWire only takes these per node from the node's
+/// own client, so the graph-wide clock settings are what another app can change.
+fn set_forced_clock(key: &str, value: u32, metadata: &MetadataMap) {
+    let metadata = metadata.borrow();
+    let Some(wrapper) = named_metadata(&metadata, "settings") else {
+        log::warn!("Cannot set {}: no settings metadata object", key);
+        return;
+    };
+    wrapper.proxy.set_property(pw::core::PW_ID_CORE, key, Some("Spa:Int"), Some(&value.to_string()));
+}
+
+fn named_metadata<'a>(metadata: &'a HashMap<u32, MetadataWrapper>, name: &str) -> Option<&'a MetadataWrapper> {
+    metadata.values().find(|m| m.name == name)
+}
+
+/// The channel count to build volume PODs with, honouring any configured override and
+/// falling back to the negotiated format before assuming stereo.
+fn channel_count_for(node: &AudioNode, overrides: &HashMap<String, u32>) -> u32 {
+    overrides
+        .get(&node.name)
+        .copied()
+        .or(node.channel_count)
+        .or(node.format.map(|f| f.channels))
+        // Only guess stereo when nothing has reported a count yet.
+        .unwrap_or(2)
+}
+
+/// Per-channel levels that bring the loudest channel to `volume` while keeping the
+/// node's current balance. Uniform when the reported channels don't match the count.
+fn channel_levels(node: &AudioNode, volume: f32, channel_count: u32) -> Vec<f32> {
+    let channels = channel_count.max(1) as usize;
+    let loudest = node.channel_volumes.iter().copied().fold(0.0, f32::max);
+    if node.channel_volumes.len() != channels || loudest <= 0.0 {
+        // Silent stereo nodes still remember their balance from before.
+        if channels == 2 && node.channel_volumes.len() == 2 {
+            return stereo_levels(volume, node.balance);
+        }
+        return vec![volume; channels];
+    }
+    node.channel_volumes
+        .iter()
+        .zip(node.channel_muted.iter().chain(std::iter::repeat(&false)))
+        .map(|(v, &muted)| if muted { 0.0 } else { v / loudest * volume })
+        .collect()
+}
+
+/// Left/right balance of the first two channels: -1.0 is left only, 1.0 right only.
+/// None when both are silent, since the balance can't be told then.
+fn balance_of(levels: &[f32]) -> Option<f32> {
+    let [left, right, ..] = *levels else { return None };
+    if left <= 0.0 && right <= 0.0 {
+        None
+    } else if right < left {
+        Some(right / left - 1.0)
+    } else {
+        Some(1.0 - left / right)
+    }
+}
+
+/// Where a node's volume is applied.
+#[derive(Debug, PartialEq)]
+enum VolumePath {
+    /// The card's route, for devices whose route is known.
+    Route {
+        device_id: u32,
+        route_index: u32,
+        route_device: u32,
+    },
+    /// The node's own Props: streams, and devices without a card route such as virtual
+    /// sinks or pro-audio nodes.
+    Props,
+    /// A device on a card with routes, before its route has been reported. Props would
+    /// stack a software volume on top of the hardware one, so nothing is sent.
+    Unknown,
+}
+
+fn volume_path(node: &AudioNode, cards: &HashMap<u32, Card>) -> VolumePath {
+    if node.is_stream {
+        return VolumePath::Props;
+    }
+    let Some(device_id) = node.device_id else { return VolumePath::Props };
+    if cards.get(&device_id).is_none_or(|card| card.routes.is_empty()) {
+        return VolumePath::Props;
+    }
+    match (node.route_index, node.route_device) {
+        (Some(route_index), Some(route_device)) => VolumePath::Route {
+            device_id,
+            route_index,
+            route_device,
+        },
+        _ => VolumePath::Unknown,
+    }
+}
+
+struct VolumeTarget {
+    path: VolumePath,
+    save: bool,
+    curve: VolumeCurve,
+}
+
+impl VolumeTarget {
+    fn of(node: &AudioNode, cards: &HashMap<u32, Card>, save: bool, curve: VolumeCurve) -> Self {
+        Self {
+            path: volume_path(node, cards),
+            save,
+            curve,
+        }
+    }
+}
+
+fn send_channel_volumes(
+    node_id: u32,
+    target: VolumeTarget,
+    levels: &[f32],
+    mute: Option<bool>,
+    nodes: &NodeMap,
+    devices: &DeviceMap,
+) {
+    match target.path {
+        VolumePath::Route {
+            device_id,
+            route_index,
+            route_device,
+        } => {
+            let devices = devices.borrow();
+            let Some(wrapper) = devices.get(&device_id) else { return };
+
+            let pod = spa::build_route_volume_pod(route_index, route_device, levels, mute, target.save, target.curve);
+            if let Some(buf) = pod
+                && let Some(pod) = spa_lib::pod::Pod::from_bytes(&buf)
+            {
+                wrapper.proxy.set_param(spa_lib::param::ParamType::Route, 0, pod);
+            }
+        }
+        VolumePath::Props => {
+            let nodes = nodes.borrow();
+            let Some(wrapper) = nodes.get(&node_id) else { return };
+            if let Some(buf) = spa::build_props_volume_pod(levels, mute, target.curve)
+                && let Some(pod) = spa_lib::pod::Pod::from_bytes(&buf)
+            {
+                wrapper.proxy.set_param(spa_lib::param::ParamType::Props, 0, pod);
+            }
+        }
+        VolumePath::Unknown => log::warn!("Not setting the volume of node {}: its card route isn't known yet", node_id),
+    }
+}
+
+fn set_volume(node_id: u32, vol: f32, state: &Arc<Mutex<AppState>>, nodes: &NodeMap, devices: &DeviceMap) {
+    let (target, levels) = {
+        let mut s = state.lock();
+        let s = &mut *s;
+        let save = s.nodes.get(&node_id).is_some_and(|n| s.persist_for(n));
+        let Some(node) = s.nodes.get_mut(&node_id) else { return };
+        // An explicit volume change supersedes whatever was saved at mute time.
+        if node.pre_mute_volume.is_some() {
+            node.pre_mute_volume = Some(vol);
+        }
+        // Sending the level would unmute a device muted through zeroed volumes;
+        // it is restored from `pre_mute_volume` on unmute instead.
+        if node.zero_volume_mute {
+            return;
+        }
+        if node.is_stream {
+            node.volume_request = Some(VolumeRequest {
+                volume: vol,
+                sent_at: Instant::now(),
+                confirmed: false,
+            });
+            node.volume_overridden = false;
+        }
+        let levels = channel_levels(node, vol, channel_count_for(node, &s.channel_overrides));
+        apply_sent_volume(node, vol, &levels);
+        (VolumeTarget::of(node, &s.cards, save, s.volume_curve), levels)
+    };
+
+    send_channel_volumes(node_id, target, &levels, None, nodes, devices);
+}
+
+fn set_channel_volumes(
+    node_id: u32,
+    levels: Vec<f32>,
+    state: &Arc<Mutex<AppState>>,
+    nodes: &NodeMap,
+    devices: &DeviceMap,
+) {
+    let volume = levels.iter().copied().fold(0.0, f32::max);
+    let target = {
+        let mut s = state.lock();
+        let s = &mut *s;
+        let save = s.nodes.get(&node_id).is_some_and(|n| s.persist_for(n));
+        let Some(node) = s.nodes.get_mut(&node_id) else { return };
+        if node.pre_mute_volume.is_some() {
+            node.pre_mute_volume = Some(volume);
+        }
+        if node.zero_volume_mute {
+            return;
+        }
+        if node.is_stream {
+            node.volume_request = Some(VolumeRequest {
+                volume,
+                sent_at: Instant::now(),
+                confirmed: false,
+            });
+            node.volume_overridden = false;
+        }
+        apply_sent_volume(node, volume, &levels);
+        VolumeTarget::of(node, &s.cards, save, s.volume_curve)
+    };
+
+    send_channel_volumes(node_id, target, &levels, None, nodes, devices);
+}
+
+/// Zero the channels being muted, saving their levels, and bring back the ones being unmuted.
+/// The node's own mute is separate and leaves these alone.
+fn set_channel_mutes(
+    node_id: u32,
+    muted: Vec<bool>,
+    state: &Arc<Mutex<AppState>>,
+    nodes: &NodeMap,
+    devices: &DeviceMap,
+) {
+    let (target, levels) = {
+        let mut s = state.lock();
+        let s = &mut *s;
+        let save = s.nodes.get(&node_id).is_some_and(|n| s.persist_for(n));
+        let Some(node) = s.nodes.get_mut(&node_id) else { return };
+        if node.zero_volume_mute {
+            return;
+        }
+
+        sync_channel_mutes(node);
+        let mut levels = node.channel_volumes.clone();
+        for (i, level) in levels.iter_mut().enumerate() {
+            let mute = muted.get(i).copied().unwrap_or(false);
+            if mute && !node.channel_muted[i] {
+                node.channel_unmute_levels[i] = *level;
+                *level = 0.0;
+            } else if !mute && node.channel_muted[i] {
+                // A channel muted while already silent comes back at the node's level.
+                let saved = node.channel_unmute_levels[i];
+                *level = if saved > 0.0 { saved } else { node.volume };
+            }
+            node.channel_muted[i] = mute;
+        }
+        let volume = levels.iter().copied().fold(0.0, f32::max);
+        apply_sent_volume(node, volume, &levels);
+        (VolumeTarget::of(node, &s.cards, save, s.volume_curve), levels)
+    };
+
+    send_channel_volumes(node_id, target, &levels, None, nodes, devices);
+}
+
+/// The level to send with a mute change. The level at mute time is remembered, so a
+/// background update arriving while muted can't change what unmute restores.
+fn mute_volume(node: &mut AudioNode, mute: bool) -> f32 {
+    if mute {
+        *node.pre_mute_volume.get_or_insert(node.volume)
+    } else {
+        node.pre_mute_volume.take().unwrap_or(node.volume)
+    }
+}
+
+fn set_mute(node_id: u32, mute: bool, state: &Arc<Mutex<AppState>>, nodes: &NodeMap, devices: &DeviceMap) {
+    let (target, levels) = {
+        let mut s = state.lock();
+        let s = &mut *s;
+        let save = s.nodes.get(&node_id).is_some_and(|n| s.persist_for(n));
+        let Some(node) = s.nodes.get_mut(&node_id) else { return };
+        let volume = mute_volume(node, mute);
+
+        // Devices known to ignore the mute prop are silenced with zero volume instead,
+        // while the others are watched to see whether they honour it.
+        node.zero_volume_mute = mute && node.ignores_mute;
+        node.mute_sent_at = (mute && !node.ignores_mute && !node.muted).then(Instant::now);
+        let volume = if node.zero_volume_mute { 0.0 } else { volume };
+
+        let levels = channel_levels(node, volume, channel_count_for(node, &s.channel_overrides));
+        (VolumeTarget::of(node, &s.cards, save, s.volume_curve), levels)
+    };
+
+    send_channel_volumes(node_id, target, &levels, Some(mute), nodes, devices);
+}
+
+// --- Helpers ---
+
+/// Log a failure and queue it for the UI to show as a toast.
+fn report_error(state: &Arc<Mutex<AppState>>, repaint: &Repaint, message: String) {
+    log::warn!("{}", message);
+    {
+        let mut s = state.lock();
+        if s.messages.len() >= MAX_MESSAGES {
+            s.messages.remove(0);
+        }
+        s.messages.push(message);
+    }
+    repaint.changed();
+}
+
+/// Wakes the UI when the state it shows has changed, at most once per
+/// `MIN_REPAINT_INTERVAL`: a burst of param or meter updates becomes one repaint now
+/// and one on the timer tick after the interval is up.
+struct RepaintState {
+    ctx: Arc<Mutex<Option<egui::Context>>>,
+    /// Set by a change the UI hasn't been asked to show yet.
+    dirty: Cell<bool>,
+    /// When we last asked the UI to repaint.
+    last: Cell<Option<Instant>>,
+}
+
+impl RepaintState {
+    fn new(ctx: Arc<Mutex<Option<egui::Context>>>) -> Self {
+        Self {
+            ctx,
+            dirty: Cell::new(false),
+            last: Cell::new(None),
+        }
+    }
+
+    /// Note a change to the shared state, repainting now if the interval allows.
+    fn changed(&self) {
+        self.dirty.set(true);
+        self.flush();
+    }
+
+    /// Repaint for pending changes once the interval since the last repaint is up.
+    fn flush(&self) {
+        if !self.dirty.get() || self.last.get().is_some_and(|at| at.elapsed() < MIN_REPAINT_INTERVAL) {
+            return;
+        }
+        self.now();
+    }
+
+    /// Repaint right away, for changes such as losing the connection that no timer follows.
+    fn now(&self) {
+        let Some(ctx) = self.ctx.lock().clone() else { return };
+        self.dirty.set(false);
+        self.last.set(Some(Instant::now()));
         ctx.request_repaint();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Route;
+
+    #[test]
+    fn unmute_restores_the_level_from_before_a_background_update() {
+        let mut node = AudioNode::for_test(1, "sink");
+        node.volume = 0.6;
+
+        assert_eq!(mute_volume(&mut node, true), 0.6);
+        apply_reported_mute(&mut node, true);
+        // A background update lowers the reported level while muted.
+        node.volume = 0.05;
+
+        assert_eq!(mute_volume(&mut node, false), 0.6);
+        assert_eq!(node.pre_mute_volume, None);
+    }
+
+    #[test]
+    fn external_unmute_forgets_the_saved_level() {
+        let mut node = AudioNode::for_test(1, "sink");
+        node.volume = 0.6;
+        mute_volume(&mut node, true);
+        apply_reported_mute(&mut node, true);
+
+        // Another client unmutes and changes the volume.
+        apply_reported_mute(&mut node, false);
+        node.volume = 0.3;
+
+        assert_eq!(mute_volume(&mut node, true), 0.3);
+        assert_eq!(mute_volume(&mut node, false), 0.3);
+    }
+
+    #[test]
+    fn unmuted_echo_before_our_mute_lands_keeps_the_saved_level() {
+        let mut node = AudioNode::for_test(1, "sink");
+        node.volume = 0.6;
+        mute_volume(&mut node, true);
+        node.mute_sent_at = Some(Instant::now());
+
+        apply_reported_mute(&mut node, false);
+
+        assert_eq!(node.pre_mute_volume, Some(0.6));
+    }
+
+    #[test]
+    fn metadata_name_from_a_plain_name() {
+        assert_eq!(metadata_node_name("alsa_output.pci").as_deref(), Some("alsa_output.pci"));
+    }
+
+    #[test]
+    fn metadata_name_from_json() {
+        assert_eq!(metadata_node_name(r#"{"name":"alsa_output.pci"}"#).as_deref(), Some("alsa_output.pci"));
+    }
+
+    #[test]
+    fn metadata_name_from_malformed_json() {
+        assert_eq!(metadata_node_name(r#"{"name":"alsa_output.pci""#), None);
+        assert_eq!(metadata_node_name(r#"{"id":5}"#), None);
+    }
+
+    /// The key of each command, in order, so results can be compared.
+    fn keys(commands: &[PwCommand]) -> Vec<Option<(u8, u32)>> {
+        commands.iter().map(command_key).collect()
+    }
+
+    #[test]
+    fn repeated_volumes_collapse_into_the_last() {
+        let commands = dedupe_commands(vec![
+            PwCommand::SetVolume(1, 0.2),
+            PwCommand::SetVolume(1, 0.4),
+            PwCommand::SetVolume(1, 0.6),
+        ]);
+
+        assert!(matches!(commands[..], [PwCommand::SetVolume(1, v)] if v == 0.6));
+    }
+
+    #[test]
+    fn dedupe_keeps_the_order_of_what_is_left() {
+        let commands = dedupe_commands(vec![
+            PwCommand::SetVolume(1, 0.2),
+            PwCommand::SetMute(2, true),
+            PwCommand::Rescan,
+            PwCommand::SetVolume(1, 0.4),
+            PwCommand::SetDefault(3),
+        ]);
+
+        assert_eq!(keys(&commands), [Some((1, 2)), None, Some((0, 1)), Some((2, 3))]);
+    }
+
+    fn output_route(route_index: u32, route_device: u32, volume: f32) -> spa::ParsedRoute {
+        spa::ParsedRoute {
+            route_index,
+            route_device,
+            direction: 1,
+            volume: Some(volume),
+            muted: Some(false),
+            channel_count: None,
+            channel_volumes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn each_output_of_a_card_takes_its_own_route() {
+        let state = Arc::new(Mutex::new(AppState::new()));
+        for (id, profile_device) in [(1, 0), (2, 1)] {
+            let mut node = AudioNode::for_test(id, &format!("out{}", id));
+            node.device_id = Some(10);
+            node.profile_device = Some(profile_device);
+            state.lock().nodes.insert(id, node);
+        }
+        state.lock().volume_curve = VolumeCurve::Linear;
+
+        update_node_from_route(10, &output_route(4, 0, 0.25), &state);
+        update_node_from_route(10, &output_route(5, 1, 0.75), &state);
+
+        let s = state.lock();
+        let (first, second) = (&s.nodes[&1], &s.nodes[&2]);
+        assert_eq!((first.route_index, first.route_device, first.volume), (Some(4), Some(0), 0.25));
+        assert_eq!((second.route_index, second.route_device, second.volume), (Some(5), Some(1), 0.75));
+
+        // Volume changes go to the route of the node they are for.
+        let cards = HashMap::from([(10, card_with_routes(10, 2))]);
+        let expected = VolumePath::Route {
+            device_id: 10,
+            route_index: 5,
+            route_device: 1,
+        };
+        assert_eq!(volume_path(second, &cards), expected);
+    }
+
+    fn card_with_routes(id: u32, count: u32) -> Card {
+        Card {
+            id,
+            name: format!("card{}", id),
+            description: String::new(),
+            profiles: Vec::new(),
+            active_profile_index: None,
+            routes: (0..count)
+                .map(|index| Route {
+                    index,
+                    direction: 1,
+                    description: String::new(),
+                    available: true,
+                    profiles: Vec::new(),
+                    devices: vec![index],
+                })
+                .collect(),
+            active_routes: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn removed_globals_leave_nothing_behind() {
+        let mut s = AppState::new();
+        // Globals share one id space, so each kind gets its own range.
+        for i in 0..500 {
+            s.nodes.insert(i, AudioNode::for_test(i, &format!("node{}", i)));
+            s.cards.insert(1000 + i, card_with_routes(1000 + i, 2));
+            s.links.insert(
+                2000 + i,
+                LinkInfo {
+                    output_node: i,
+                    output_port: 3000 + i,
+                    input_node: (i + 1) % 500,
+                    input_port: 4000 + i,
+                    state: LinkState::Connecting,
+                },
+            );
+            for (port, is_output) in [(3000 + i, true), (4000 + i, false)] {
+                let info = PortInfo {
+                    node_id: i,
+                    is_output,
+                    name: format!("port{}", port),
+                    channel: None,
+                };
+                s.ports.insert(port, info);
+            }
+        }
+
+        for id in 0..5000 {
+            let shown = s.nodes.contains_key(&id) || s.cards.contains_key(&id) || s.links.contains_key(&id);
+            assert_eq!(forget_global(&mut s, id), shown);
+        }
+        assert!(s.nodes.is_empty() && s.cards.is_empty() && s.links.is_empty() && s.ports.is_empty());
+
+        // Removing an id twice, or one never seen, is harmless.
+        assert!(!forget_global(&mut s, 7));
+    }
+
+    #[test]
+    fn a_sink_without_a_device_gets_a_props_volume() {
+        let node = AudioNode::for_test(1, "virtual");
+        assert_eq!(node.device_id, None);
+        assert_eq!(volume_path(&node, &HashMap::new()), VolumePath::Props);
+    }
+
+    #[test]
+    fn only_cards_without_routes_fall_back_to_props() {
+        let mut node = AudioNode::for_test(1, "out");
+        node.device_id = Some(10);
+
+        let pro_audio = HashMap::from([(10, card_with_routes(10, 0))]);
+        assert_eq!(volume_path(&node, &pro_audio), VolumePath::Props);
+
+        // The card has ports but hasn't said which one this node is on yet.
+        let alsa = HashMap::from([(10, card_with_routes(10, 2))]);
+        assert_eq!(volume_path(&node, &alsa), VolumePath::Unknown);
+    }
+
+    #[test]
+    fn a_burst_leaves_each_node_its_final_commands() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        for step in 1..=100 {
+            for node_id in 1..=5 {
+                tx.send(PwCommand::SetVolume(node_id, step as f32 / 100.0)).unwrap();
+                tx.send(PwCommand::SetMute(node_id, step % 2 == 0)).unwrap();
+            }
+        }
+
+        let commands = dedupe_commands(rx.try_iter().collect());
+
+        assert_eq!(commands.len(), 10);
+        for node_id in 1..=5 {
+            let volumes: Vec<f32> = commands
+                .iter()
+                .filter_map(|cmd| match *cmd {
+                    PwCommand::SetVolume(id, v) if id == node_id => Some(v),
+                    _ => None,
+                })
+                .collect();
+            let mutes: Vec<bool> = commands
+                .iter()
+                .filter_map(|cmd| match *cmd {
+                    PwCommand::SetMute(id, m) if id == node_id => Some(m),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(volumes, [1.0]);
+            assert_eq!(mutes, [true]);
+        }
+    }
+
+    #[test]
+    fn mono_source_gets_a_single_channel() {
+        let mut node = AudioNode::for_test(1, "mic");
+        node.is_sink = false;
+        node.channel_count = Some(1);
+
+        let levels = channel_levels(&node, 0.5, channel_count_for(&node, &HashMap::new()));
+
+        assert_eq!(levels, [0.5]);
+    }
+
+    #[test]
+    fn unknown_channel_count_falls_back_to_stereo() {
+        let node = AudioNode::for_test(1, "sink");
+        assert_eq!(channel_count_for(&node, &HashMap::new()), 2);
+    }
+
+    #[test]
+    fn metadata_name_from_spaced_json() {
+        assert_eq!(metadata_node_name(r#"{ "name" : "alsa_output.pci" }"#).as_deref(), Some("alsa_output.pci"));
+    }
+
+    #[test]
+    fn metadata_name_with_escaped_characters() {
+        assert_eq!(metadata_node_name(r#"{"name":"my \"sink\"\u00e9"}"#).as_deref(), Some("my \"sink\"é"));
+    }
+
+    #[test]
+    fn changes_within_the_repaint_interval_wait_for_the_next_flush() {
+        let repaint = RepaintState::new(Arc::new(Mutex::new(Some(egui::Context::default()))));
+        repaint.changed();
+        assert!(!repaint.dirty.get());
+
+        repaint.changed();
+        repaint.flush();
+        assert!(repaint.dirty.get());
+
+        std::thread::sleep(MIN_REPAINT_INTERVAL);
+        repaint.flush();
+        assert!(!repaint.dirty.get());
+    }
+}
@@ -0,0 +1,104 @@
+//! Desktop notification with the default output's volume after it is changed from Copper.
+
+use crate::state::AppState;
+use crossbeam_channel::Sender;
+use notify_rust::{Hint, Notification};
+use parking_lot::Mutex;
+use std::cell::Cell;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long changes have to stop before the notification goes out, so a slider drag
+/// produces one notification with the final volume.
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+const TIMEOUT_MS: i32 = 1500;
+
+/// One notification for the worker thread to show.
+struct Shown {
+    summary: String,
+    body: String,
+    icon: &'static str,
+    percent: i32,
+}
+
+pub struct Notifier {
+    /// The sink whose notification is due, and when it last changed.
+    pending: Cell<Option<(u32, Instant)>>,
+    tx: Sender<Shown>,
+}
+
+impl Notifier {
+    /// Showing a notification is a blocking D-Bus call, so it happens on a thread of its
+    /// own; the thread ends when the notifier is dropped.
+    pub fn new() -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded::<Shown>();
+        std::thread::spawn(move || {
+            // Each notification replaces the previous one instead of stacking up.
+            let mut last_id = None;
+            for shown in rx {
+                let mut notification = Notification::new();
+                notification
+                    .appname("Copper")
+                    .summary(&shown.summary)
+                    .body(&shown.body)
+                    .icon(shown.icon)
+                    .hint(Hint::CustomInt("value".to_string(), shown.percent))
+                    .hint(Hint::Transient(true))
+                    .timeout(TIMEOUT_MS);
+                if let Some(id) = last_id {
+                    notification.id(id);
+                }
+                match notification.show() {
+                    Ok(handle) => last_id = Some(handle.id()),
+                    Err(e) => log::warn!("Failed to show a volume notification: {}", e),
+                }
+            }
+        });
+
+        Self {
+            pending: Cell::new(None),
+            tx,
+        }
+    }
+
+    /// Note a volume or mute change; only the default output is announced, and only if enabled.
+    pub fn volume_changed(&self, node_id: u32, state: &Arc<Mutex<AppState>>) {
+        let s = state.lock();
+        if s.volume_notifications
+            && s.nodes.get(&node_id).is_some_and(|n| n.is_default && n.is_sink && !n.is_stream)
+        {
+            self.pending.set(Some((node_id, Instant::now())));
+        }
+    }
+
+    /// Send the pending notification once changes have settled.
+    pub fn tick(&self, state: &Arc<Mutex<AppState>>) {
+        let Some((node_id, at)) = self.pending.get() else { return };
+        if at.elapsed() < SETTLE_DELAY {
+            return;
+        }
+        self.pending.set(None);
+
+        let s = state.lock();
+        let Some(node) = s.nodes.get(&node_id) else { return };
+        let percent = (node.volume * 100.0).round() as i32;
+        let (body, icon) = if node.muted {
+            ("Muted".to_string(), "audio-volume-muted")
+        } else {
+            let icon = match percent {
+                0 => "audio-volume-muted",
+                1..=33 => "audio-volume-low",
+                34..=66 => "audio-volume-medium",
+                _ => "audio-volume-high",
+            };
+            (format!("Volume {}%", percent), icon)
+        };
+
+        let _ = self.tx.send(Shown {
+            summary: node.description.clone(),
+            body,
+            icon,
+            percent,
+        });
+    }
+}
@@ -1,5 +1,6 @@
 //! SPA POD parsing and building utilities for PipeWire audio control.
 
+use crate::state::VolumeCurve;
 use libspa as spa;
 use libspa_sys as spa_sys;
 use std::mem::MaybeUninit;
@@ -13,7 +14,12 @@ pub const SPA_PROP_CHANNEL_VOLUMES: u32 = 65544;
 const ROUTE_KEY_INDEX: u32 = 1;
 const ROUTE_KEY_DIRECTION: u32 = 2;
 const ROUTE_KEY_DEVICE: u32 = 3;
+const ROUTE_KEY_NAME: u32 = 4;
+const ROUTE_KEY_DESCRIPTION: u32 = 5;
+const ROUTE_KEY_AVAILABLE: u32 = 7;
+const ROUTE_KEY_PROFILES: u32 = 9;
 const ROUTE_KEY_PROPS: u32 = 10;
+const ROUTE_KEY_DEVICES: u32 = 11;
 const ROUTE_KEY_SAVE: u32 = 13;
 
 // SPA object types
@@ -25,8 +31,9 @@ const SPA_TYPE_OBJECT_PARAM_ROUTE: u32 = 262153;
 const PROFILE_KEY_INDEX: u32 = 1;
 const PROFILE_KEY_NAME: u32 = 2;
 const PROFILE_KEY_DESCRIPTION: u32 = 3;
+const PROFILE_KEY_PRIORITY: u32 = 4;
 const PROFILE_KEY_AVAILABLE: u32 = 5;
-const PROFILE_KEY_SAVE: u32 = 7;
+const PROFILE_KEY_SAVE: u32 = 8;
 
 /// Parsed audio properties from a node or route.
 #[derive(Debug, Default)]
@@ -34,6 +41,8 @@ pub struct ParsedProps {
     pub volume: Option<f32>,
     pub muted: Option<bool>,
     pub channel_count: Option<u32>,
+    /// Linear per-channel volumes; empty when the POD had none.
+    pub channel_volumes: Vec<f32>,
 }
 
 /// Parsed route information from a device.
@@ -45,6 +54,20 @@ pub struct ParsedRoute {
     pub volume: Option<f32>,
     pub muted: Option<bool>,
     pub channel_count: Option<u32>,
+    pub channel_volumes: Vec<f32>,
+}
+
+/// A route from a device's EnumRoute list, with the profiles that provide it.
+#[derive(Debug)]
+pub struct ParsedEnumRoute {
+    pub index: u32,
+    pub direction: u32,
+    pub description: String,
+    /// False only when the port is known to be unplugged.
+    pub available: bool,
+    pub profiles: Vec<u32>,
+    /// Route devices (`card.profile.device`) the route can be selected on.
+    pub devices: Vec<u32>,
 }
 
 /// Parsed profile information from a device.
@@ -52,34 +75,74 @@ pub struct ParsedRoute {
 pub struct ParsedProfile {
     pub index: u32,
     pub description: String,
+    pub priority: u32,
     pub available: bool,
 }
 
-/// Read the first float value from a SPA float array, returning (value, count).
-unsafe fn read_float_array_first(pod: *mut spa_sys::spa_pod) -> Option<(f32, u32)> {
+/// Parsed raw audio format from a node's Format parameter.
+#[derive(Debug)]
+pub struct ParsedFormat {
+    pub rate: u32,
+    pub channels: u32,
+    /// A `SPA_AUDIO_FORMAT_*` id.
+    pub format: u32,
+}
+
+/// Read every value of a SPA float array.
+unsafe fn read_float_array(pod: *mut spa_sys::spa_pod) -> Vec<f32> {
     if unsafe { (*pod).type_ } != spa_sys::SPA_TYPE_Array {
-        return None;
+        return Vec::new();
     }
 
     let array = pod as *mut spa_sys::spa_pod_array;
     let body = unsafe { &(*array).body };
 
-    if (*body).child.type_ != spa_sys::SPA_TYPE_Float {
-        return None;
+    if body.child.type_ != spa_sys::SPA_TYPE_Float {
+        return Vec::new();
     }
 
     let pod_size = unsafe { (*array).pod.size };
     let body_size = std::mem::size_of::<spa_sys::spa_pod_array_body>() as u32;
+    if pod_size <= body_size {
+        return Vec::new();
+    }
 
+    let count = ((pod_size - body_size) / 4) as usize;
+    let data_ptr = unsafe { (body as *const _ as *const u8).add(body_size as usize) } as *const f32;
+    (0..count).map(|i| unsafe { data_ptr.add(i).read_unaligned() }).collect()
+}
+
+/// Read every value of a SPA int array.
+unsafe fn read_int_array(pod: *mut spa_sys::spa_pod) -> Vec<i32> {
+    if unsafe { (*pod).type_ } != spa_sys::SPA_TYPE_Array {
+        return Vec::new();
+    }
+
+    let array = pod as *mut spa_sys::spa_pod_array;
+    let body = unsafe { &(*array).body };
+
+    if body.child.type_ != spa_sys::SPA_TYPE_Int {
+        return Vec::new();
+    }
+
+    let pod_size = unsafe { (*array).pod.size };
+    let body_size = std::mem::size_of::<spa_sys::spa_pod_array_body>() as u32;
     if pod_size <= body_size {
-        return None;
+        return Vec::new();
     }
 
-    let count = (pod_size - body_size) / 4;
-    let data_ptr = unsafe { (body as *const _ as *const u8).add(body_size as usize) };
-    let value = unsafe { *(data_ptr as *const f32) };
+    let count = ((pod_size - body_size) / 4) as usize;
+    let data_ptr = unsafe { (body as *const _ as *const u8).add(body_size as usize) } as *const i32;
+    (0..count).map(|i| unsafe { data_ptr.add(i).read_unaligned() }).collect()
+}
 
-    Some((value, count))
+/// Read a SPA string value.
+unsafe fn read_string(pod: *mut spa_sys::spa_pod) -> Option<String> {
+    let mut s: *const std::os::raw::c_char = std::ptr::null();
+    if unsafe { spa_sys::spa_pod_get_string(pod, &mut s) } < 0 || s.is_null() {
+        return None;
+    }
+    Some(unsafe { std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned() })
 }
 
 /// Parse audio properties (volume, mute, channel count) from a SPA POD object.
@@ -94,6 +157,7 @@ pub unsafe fn parse_props(pod: *mut spa_sys::spa_pod) -> ParsedProps {
     let body = unsafe { &(*obj).body };
     let size = unsafe { (*obj).pod.size };
     let mut iter = unsafe { spa_sys::spa_pod_prop_first(body) };
+    let mut master = None;
 
     while unsafe { spa_sys::spa_pod_prop_is_inside(body, size, iter) } {
         let key = unsafe { (*iter).key };
@@ -101,15 +165,16 @@ pub unsafe fn parse_props(pod: *mut spa_sys::spa_pod) -> ParsedProps {
 
         match key {
             SPA_PROP_CHANNEL_VOLUMES => {
-                if let Some((vol, count)) = unsafe { read_float_array_first(value_ptr) } {
-                    result.volume = Some(vol);
-                    result.channel_count = Some(count);
+                let volumes = unsafe { read_float_array(value_ptr) };
+                if !volumes.is_empty() {
+                    result.channel_count = Some(volumes.len() as u32);
+                    result.channel_volumes = volumes;
                 }
             }
-            SPA_PROP_VOLUME if result.volume.is_none() => {
+            SPA_PROP_VOLUME => {
                 let mut f: f32 = 0.0;
                 if unsafe { spa_sys::spa_pod_get_float(value_ptr, &mut f) } >= 0 {
-                    result.volume = Some(f);
+                    master = Some(f);
                 }
             }
             SPA_PROP_MUTE => {
@@ -124,9 +189,26 @@ pub unsafe fn parse_props(pod: *mut spa_sys::spa_pod) -> ParsedProps {
         iter = unsafe { spa_sys::spa_pod_prop_next(iter) };
     }
 
+    // Decided after the loop, so the order of the two props in the POD doesn't matter.
+    result.volume = overall_volume(master, &mut result.channel_volumes);
+
     result
 }
 
+/// The level a node plays at: the master `volume` is a gain on top of every channel, so
+/// it is folded into the channel levels, and the loudest channel is the overall level so
+/// balance doesn't move it. Without a channel array the master alone is the level.
+fn overall_volume(master: Option<f32>, channel_volumes: &mut [f32]) -> Option<f32> {
+    if channel_volumes.is_empty() {
+        return master;
+    }
+    let master = master.unwrap_or(1.0);
+    for v in channel_volumes.iter_mut() {
+        *v *= master;
+    }
+    Some(channel_volumes.iter().copied().fold(0.0, f32::max))
+}
+
 /// Parse route information from a SPA Route parameter POD.
 pub unsafe fn parse_route(pod: *const spa_sys::spa_pod) -> Option<ParsedRoute> {
     if unsafe { (*pod).type_ } != spa_sys::SPA_TYPE_Object {
@@ -144,6 +226,7 @@ pub unsafe fn parse_route(pod: *const spa_sys::spa_pod) -> Option<ParsedRoute> {
     let mut volume = None;
     let mut muted = None;
     let mut channel_count = None;
+    let mut channel_volumes = Vec::new();
 
     while unsafe { spa_sys::spa_pod_prop_is_inside(body, size, iter) } {
         let key = unsafe { (*iter).key };
@@ -173,6 +256,7 @@ pub unsafe fn parse_route(pod: *const spa_sys::spa_pod) -> Option<ParsedRoute> {
                 volume = props.volume;
                 muted = props.muted;
                 channel_count = props.channel_count;
+                channel_volumes = props.channel_volumes;
             }
             _ => {}
         }
@@ -187,9 +271,178 @@ pub unsafe fn parse_route(pod: *const spa_sys::spa_pod) -> Option<ParsedRoute> {
         volume,
         muted,
         channel_count,
+        channel_volumes,
+    })
+}
+
+/// Parse a route from a SPA EnumRoute parameter POD.
+pub unsafe fn parse_enum_route(pod: *const spa_sys::spa_pod) -> Option<ParsedEnumRoute> {
+    if unsafe { (*pod).type_ } != spa_sys::SPA_TYPE_Object {
+        return None;
+    }
+
+    let obj = pod as *mut spa_sys::spa_pod_object;
+    let body = unsafe { &(*obj).body };
+    let size = unsafe { (*obj).pod.size };
+    let mut iter = unsafe { spa_sys::spa_pod_prop_first(body) };
+
+    let mut index = None;
+    let mut direction = None;
+    let mut name = None;
+    let mut description = None;
+    let mut available = true;
+    let mut profiles = Vec::new();
+    let mut devices = Vec::new();
+
+    while unsafe { spa_sys::spa_pod_prop_is_inside(body, size, iter) } {
+        let key = unsafe { (*iter).key };
+        let value_ptr = unsafe { &mut (*iter).value as *mut spa_sys::spa_pod };
+
+        match key {
+            ROUTE_KEY_INDEX => {
+                let mut i: i32 = 0;
+                if unsafe { spa_sys::spa_pod_get_int(value_ptr, &mut i) } >= 0 {
+                    index = Some(i as u32);
+                }
+            }
+            ROUTE_KEY_DIRECTION => {
+                let mut i: u32 = 0;
+                if unsafe { spa_sys::spa_pod_get_id(value_ptr, &mut i) } >= 0 {
+                    direction = Some(i);
+                }
+            }
+            ROUTE_KEY_NAME => name = unsafe { read_string(value_ptr) },
+            ROUTE_KEY_DESCRIPTION => description = unsafe { read_string(value_ptr) },
+            ROUTE_KEY_AVAILABLE => {
+                let mut i: u32 = 0;
+                if unsafe { spa_sys::spa_pod_get_id(value_ptr, &mut i) } >= 0 {
+                    // 0 = Unknown, 1 = No, 2 = Yes
+                    available = i != 1;
+                }
+            }
+            ROUTE_KEY_PROFILES => {
+                profiles = unsafe { read_int_array(value_ptr) }
+                    .into_iter()
+                    .map(|p| p.max(0) as u32)
+                    .collect();
+            }
+            ROUTE_KEY_DEVICES => {
+                devices = unsafe { read_int_array(value_ptr) }
+                    .into_iter()
+                    .map(|d| d.max(0) as u32)
+                    .collect();
+            }
+            _ => {}
+        }
+
+        iter = unsafe { spa_sys::spa_pod_prop_next(iter) };
+    }
+
+    let index = index?;
+    let description = description
+        .filter(|d| !d.is_empty())
+        .or(name.filter(|n| !n.is_empty()))
+        .unwrap_or_else(|| format!("Port {}", index));
+
+    Some(ParsedEnumRoute {
+        index,
+        direction: direction?,
+        description,
+        available,
+        profiles,
+        devices,
+    })
+}
+
+/// Parse a raw audio Format POD. Returns `None` unless rate, channels and sample format are
+/// all fixed values; ranges and choices (as in EnumFormat) don't describe a running node.
+pub unsafe fn parse_format(pod: *const spa_sys::spa_pod) -> Option<ParsedFormat> {
+    if unsafe { (*pod).type_ } != spa_sys::SPA_TYPE_Object {
+        return None;
+    }
+
+    let obj = pod as *mut spa_sys::spa_pod_object;
+    if unsafe { (*obj).body.type_ } != spa_sys::SPA_TYPE_OBJECT_Format {
+        return None;
+    }
+    let body = unsafe { &(*obj).body };
+    let size = unsafe { (*obj).pod.size };
+    let mut iter = unsafe { spa_sys::spa_pod_prop_first(body) };
+
+    let mut media_subtype = None;
+    let mut rate = None;
+    let mut channels = None;
+    let mut format = None;
+
+    while unsafe { spa_sys::spa_pod_prop_is_inside(body, size, iter) } {
+        let key = unsafe { (*iter).key };
+        let value_ptr = unsafe { &mut (*iter).value as *mut spa_sys::spa_pod };
+
+        match key {
+            spa_sys::SPA_FORMAT_mediaSubtype => {
+                let mut id: u32 = 0;
+                if unsafe { spa_sys::spa_pod_get_id(value_ptr, &mut id) } >= 0 {
+                    media_subtype = Some(id);
+                }
+            }
+            spa_sys::SPA_FORMAT_AUDIO_format => {
+                let mut id: u32 = 0;
+                if unsafe { spa_sys::spa_pod_get_id(value_ptr, &mut id) } >= 0 {
+                    format = Some(id);
+                }
+            }
+            spa_sys::SPA_FORMAT_AUDIO_rate => {
+                let mut i: i32 = 0;
+                if unsafe { spa_sys::spa_pod_get_int(value_ptr, &mut i) } >= 0 && i > 0 {
+                    rate = Some(i as u32);
+                }
+            }
+            spa_sys::SPA_FORMAT_AUDIO_channels => {
+                let mut i: i32 = 0;
+                if unsafe { spa_sys::spa_pod_get_int(value_ptr, &mut i) } >= 0 && i > 0 {
+                    channels = Some(i as u32);
+                }
+            }
+            _ => {}
+        }
+
+        iter = unsafe { spa_sys::spa_pod_prop_next(iter) };
+    }
+
+    // Compressed passthrough formats have no sample format to show.
+    if media_subtype != Some(spa_sys::SPA_MEDIA_SUBTYPE_raw) {
+        return None;
+    }
+
+    Some(ParsedFormat {
+        rate: rate?,
+        channels: channels?,
+        format: format?,
     })
 }
 
+/// Short name for a `SPA_AUDIO_FORMAT_*` id, in native byte order.
+pub fn sample_format_name(format: u32) -> &'static str {
+    match format {
+        spa_sys::SPA_AUDIO_FORMAT_U8 => "u8",
+        spa_sys::SPA_AUDIO_FORMAT_S8 => "s8",
+        spa_sys::SPA_AUDIO_FORMAT_S16 => "s16",
+        spa_sys::SPA_AUDIO_FORMAT_S24 => "s24",
+        spa_sys::SPA_AUDIO_FORMAT_S24_32 => "s24_32",
+        spa_sys::SPA_AUDIO_FORMAT_S32 => "s32",
+        spa_sys::SPA_AUDIO_FORMAT_F32 => "float32",
+        spa_sys::SPA_AUDIO_FORMAT_F64 => "float64",
+        spa_sys::SPA_AUDIO_FORMAT_U8P => "u8 planar",
+        spa_sys::SPA_AUDIO_FORMAT_S16P => "s16 planar",
+        spa_sys::SPA_AUDIO_FORMAT_S24P => "s24 planar",
+        spa_sys::SPA_AUDIO_FORMAT_S24_32P => "s24_32 planar",
+        spa_sys::SPA_AUDIO_FORMAT_S32P => "s32 planar",
+        spa_sys::SPA_AUDIO_FORMAT_F32P => "float32 planar",
+        spa_sys::SPA_AUDIO_FORMAT_F64P => "float64 planar",
+        _ => "other",
+    }
+}
+
 /// Parse profile information from a SPA Profile parameter POD.
 pub unsafe fn parse_profile(pod: *const spa_sys::spa_pod) -> Option<ParsedProfile> {
     if unsafe { (*pod).type_ } != spa_sys::SPA_TYPE_Object {
@@ -202,7 +455,9 @@ pub unsafe fn parse_profile(pod: *const spa_sys::spa_pod) -> Option<ParsedProfil
     let mut iter = unsafe { spa_sys::spa_pod_prop_first(body) };
 
     let mut index = None;
+    let mut name = None;
     let mut description = None;
+    let mut priority = 0;
     let mut available = true;
 
     while unsafe { spa_sys::spa_pod_prop_is_inside(body, size, iter) } {
@@ -216,18 +471,19 @@ pub unsafe fn parse_profile(pod: *const spa_sys::spa_pod) -> Option<ParsedProfil
                     index = Some(i as u32);
                 }
             }
-            PROFILE_KEY_NAME => {}
-            PROFILE_KEY_DESCRIPTION => {
-                let mut s: *const std::os::raw::c_char = std::ptr::null();
-                if unsafe { spa_sys::spa_pod_get_string(value_ptr, &mut s) } >= 0 {
-                    description = Some(unsafe { std::ffi::CStr::from_ptr(s).to_string_lossy().into_owned() });
+            PROFILE_KEY_NAME => name = unsafe { read_string(value_ptr) },
+            PROFILE_KEY_DESCRIPTION => description = unsafe { read_string(value_ptr) },
+            PROFILE_KEY_PRIORITY => {
+                let mut i: i32 = 0;
+                if unsafe { spa_sys::spa_pod_get_int(value_ptr, &mut i) } >= 0 {
+                    priority = i.max(0) as u32;
                 }
             }
             PROFILE_KEY_AVAILABLE => {
                 let mut i: u32 = 0;
                 if unsafe { spa_sys::spa_pod_get_id(value_ptr, &mut i) } >= 0 {
-                    // 0 = No, 1 = Yes, 2 = Unknown
-                    available = i != 0;
+                    // 0 = Unknown, 1 = No, 2 = Yes
+                    available = i != 1;
                 }
             }
             _ => {}
@@ -236,9 +492,17 @@ pub unsafe fn parse_profile(pod: *const spa_sys::spa_pod) -> Option<ParsedProfil
         iter = unsafe { spa_sys::spa_pod_prop_next(iter) };
     }
 
+    let index = index?;
+    // Some profiles carry only a name; fall back to it (or the index) so none render blank.
+    let description = description
+        .filter(|d| !d.is_empty())
+        .or(name.filter(|n| !n.is_empty()))
+        .unwrap_or_else(|| format!("Profile {}", index));
+
     Some(ParsedProfile {
-        index: index?,
-        description: description.unwrap_or_default(),
+        index,
+        description,
+        priority,
         available,
     })
 }
@@ -269,16 +533,45 @@ pub fn build_profile_pod(index: u32) -> Option<Vec<u8>> {
     Some(buf)
 }
 
-/// Build a Route parameter POD for setting device volume.
+/// Build a Route parameter POD that switches `route_device` to the route (port) at `route_index`.
+pub fn build_route_select_pod(route_index: u32, route_device: u32) -> Option<Vec<u8>> {
+    let mut buf = Vec::with_capacity(128);
+    let mut builder = spa::pod::builder::Builder::new(&mut buf);
+
+    unsafe {
+        let mut frame: MaybeUninit<spa_sys::spa_pod_frame> = MaybeUninit::uninit();
+
+        builder
+            .push_object(&mut frame, SPA_TYPE_OBJECT_PARAM_ROUTE, spa::param::ParamType::Route.as_raw())
+            .ok()?;
+
+        builder.add_prop(ROUTE_KEY_INDEX, 0).ok()?;
+        builder.add_int(route_index as i32).ok()?;
+
+        builder.add_prop(ROUTE_KEY_DEVICE, 0).ok()?;
+        builder.add_int(route_device as i32).ok()?;
+
+        // A port picked by hand should stick, like a profile
+        builder.add_prop(ROUTE_KEY_SAVE, 0).ok()?;
+        builder.add_bool(true).ok()?;
+
+        builder.pop(&mut frame.assume_init());
+    }
+
+    Some(buf)
+}
+
+/// Build a Route parameter POD for setting device volume. Volumes are in slider units,
+/// converted to linear gain through `curve`.
 pub fn build_route_volume_pod(
     route_index: u32,
     route_device: u32,
-    channel_count: u32,
-    volume: f32,
+    channel_volumes: &[f32],
     mute: Option<bool>,
+    save: bool,
+    curve: VolumeCurve,
 ) -> Option<Vec<u8>> {
-    let vol_linear = volume.powi(3);
-    let channels = channel_count.max(2) as usize;
+    let linear: Vec<f32> = channel_volumes.iter().map(|&v| curve.to_gain(v)).collect();
 
     let mut buf = Vec::with_capacity(1024);
     let mut builder = spa::pod::builder::Builder::new(&mut buf);
@@ -306,16 +599,11 @@ pub fn build_route_volume_pod(
             .push_object(&mut props_frame, SPA_TYPE_OBJECT_PROPS, spa::param::ParamType::Route.as_raw())
             .ok()?;
 
-        // Channel volumes
+        // Channel volumes, with the master gain reset so they are the whole level
+        builder.add_prop(SPA_PROP_VOLUME, 0).ok()?;
+        builder.add_float(1.0).ok()?;
         builder.add_prop(SPA_PROP_CHANNEL_VOLUMES, 0).ok()?;
-        let floats: Vec<f32> = vec![vol_linear; channels];
-        spa_sys::spa_pod_builder_array(
-            builder.as_raw() as *const _ as *mut _,
-            4,
-            spa_sys::SPA_TYPE_Float,
-            floats.len() as u32,
-            floats.as_ptr() as *const std::ffi::c_void,
-        );
+        add_float_array(&mut builder, &linear)?;
 
         // Mute (optional)
         if let Some(m) = mute {
@@ -325,9 +613,9 @@ pub fn build_route_volume_pod(
 
         builder.pop(&mut props_frame.assume_init());
 
-        // Save = true (persist the change)
+        // Whether the session manager should persist the change
         builder.add_prop(ROUTE_KEY_SAVE, 0).ok()?;
-        builder.add_bool(true).ok()?;
+        builder.add_bool(save).ok()?;
 
         builder.pop(&mut frame.assume_init());
     }
@@ -335,14 +623,54 @@ pub fn build_route_volume_pod(
     Some(buf)
 }
 
-/// Build a Props parameter POD for setting node volume.
-pub fn build_props_volume_pod(
-    channel_count: u32,
-    volume: f32,
-    mute: Option<bool>,
-) -> Option<Vec<u8>> {
-    let vol_linear = volume.powi(3);
-    let channels = channel_count.max(2) as usize;
+/// Build an EnumFormat POD for interleaved 32-bit float audio.
+pub fn build_audio_format_pod(rate: u32, channels: u32) -> Option<Vec<u8>> {
+    let mut buf = Vec::with_capacity(256);
+    let mut builder = spa::pod::builder::Builder::new(&mut buf);
+
+    unsafe {
+        let mut frame: MaybeUninit<spa_sys::spa_pod_frame> = MaybeUninit::uninit();
+
+        builder
+            .push_object(&mut frame, spa_sys::SPA_TYPE_OBJECT_Format, spa_sys::SPA_PARAM_EnumFormat)
+            .ok()?;
+
+        builder.add_prop(spa_sys::SPA_FORMAT_mediaType, 0).ok()?;
+        builder.add_id(spa::utils::Id(spa_sys::SPA_MEDIA_TYPE_audio)).ok()?;
+        builder.add_prop(spa_sys::SPA_FORMAT_mediaSubtype, 0).ok()?;
+        builder.add_id(spa::utils::Id(spa_sys::SPA_MEDIA_SUBTYPE_raw)).ok()?;
+        builder.add_prop(spa_sys::SPA_FORMAT_AUDIO_format, 0).ok()?;
+        builder.add_id(spa::utils::Id(spa_sys::SPA_AUDIO_FORMAT_F32)).ok()?;
+        builder.add_prop(spa_sys::SPA_FORMAT_AUDIO_rate, 0).ok()?;
+        builder.add_int(rate as i32).ok()?;
+        builder.add_prop(spa_sys::SPA_FORMAT_AUDIO_channels, 0).ok()?;
+        builder.add_int(channels as i32).ok()?;
+
+        builder.pop(&mut frame.assume_init());
+    }
+
+    Some(buf)
+}
+
+/// Append a float array as the value of the prop just added, through the builder's
+/// own array API so errors (e.g. a full buffer) are reported like any other value.
+fn add_float_array(builder: &mut spa::pod::builder::Builder, values: &[f32]) -> Option<()> {
+    // SAFETY: the slice provides `values.len()` contiguous f32 elements of the declared size.
+    unsafe {
+        builder.add_array(
+            std::mem::size_of::<f32>() as u32,
+            spa_sys::SPA_TYPE_Float,
+            values.len() as u32,
+            values.as_ptr() as *const std::ffi::c_void,
+        )
+    }
+    .ok()
+}
+
+/// Build a Props parameter POD for setting node volume, from slider units like
+/// `build_route_volume_pod`.
+pub fn build_props_volume_pod(channel_volumes: &[f32], mute: Option<bool>, curve: VolumeCurve) -> Option<Vec<u8>> {
+    let linear: Vec<f32> = channel_volumes.iter().map(|&v| curve.to_gain(v)).collect();
 
     let mut buf = Vec::with_capacity(512);
     let mut builder = spa::pod::builder::Builder::new(&mut buf);
@@ -354,16 +682,11 @@ pub fn build_props_volume_pod(
             .push_object(&mut frame, SPA_TYPE_OBJECT_PROPS, spa::param::ParamType::Props.as_raw())
             .ok()?;
 
-        // Channel volumes
+        // Channel volumes, with the master gain reset so they are the whole level
+        builder.add_prop(SPA_PROP_VOLUME, 0).ok()?;
+        builder.add_float(1.0).ok()?;
         builder.add_prop(SPA_PROP_CHANNEL_VOLUMES, 0).ok()?;
-        let floats: Vec<f32> = vec![vol_linear; channels];
-        spa_sys::spa_pod_builder_array(
-            builder.as_raw() as *const _ as *mut _,
-            4,
-            spa_sys::SPA_TYPE_Float,
-            floats.len() as u32,
-            floats.as_ptr() as *const std::ffi::c_void,
-        );
+        add_float_array(&mut builder, &linear)?;
 
         // Mute (optional)
         if let Some(m) = mute {
@@ -376,3 +699,146 @@ pub fn build_props_volume_pod(
 
     Some(buf)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Props object with the master volume and channel volumes in the given order.
+    fn props_pod(master: f32, channels: &[f32], master_first: bool) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut builder = spa::pod::builder::Builder::new(&mut buf);
+        unsafe {
+            let mut frame: MaybeUninit<spa_sys::spa_pod_frame> = MaybeUninit::uninit();
+            builder
+                .push_object(&mut frame, SPA_TYPE_OBJECT_PROPS, spa::param::ParamType::Props.as_raw())
+                .unwrap();
+            if master_first {
+                builder.add_prop(SPA_PROP_VOLUME, 0).unwrap();
+                builder.add_float(master).unwrap();
+            }
+            builder.add_prop(SPA_PROP_CHANNEL_VOLUMES, 0).unwrap();
+            add_float_array(&mut builder, channels).unwrap();
+            if !master_first {
+                builder.add_prop(SPA_PROP_VOLUME, 0).unwrap();
+                builder.add_float(master).unwrap();
+            }
+            builder.pop(&mut frame.assume_init());
+        }
+        buf
+    }
+
+    fn parse(buf: &mut [u8]) -> ParsedProps {
+        unsafe { parse_props(buf.as_mut_ptr() as *mut spa_sys::spa_pod) }
+    }
+
+    #[test]
+    fn master_volume_scales_the_channels() {
+        let mut channels = [0.8, 0.4];
+        assert_eq!(overall_volume(Some(0.5), &mut channels), Some(0.4));
+        assert_eq!(channels, [0.4, 0.2]);
+    }
+
+    #[test]
+    fn master_volume_alone_without_channels() {
+        assert_eq!(overall_volume(Some(0.7), &mut []), Some(0.7));
+        assert_eq!(overall_volume(None, &mut []), None);
+    }
+
+    #[test]
+    fn channels_alone_without_master() {
+        let mut channels = [0.3, 0.6];
+        assert_eq!(overall_volume(None, &mut channels), Some(0.6));
+        assert_eq!(channels, [0.3, 0.6]);
+    }
+
+    #[test]
+    fn parse_props_honours_master_in_either_order() {
+        for master_first in [true, false] {
+            let props = parse(&mut props_pod(0.5, &[1.0, 0.5], master_first));
+            assert_eq!(props.volume, Some(0.5));
+            assert_eq!(props.channel_count, Some(2));
+            assert_eq!(props.channel_volumes, vec![0.5, 0.25]);
+        }
+    }
+
+    #[test]
+    fn props_volume_pod_round_trips() {
+        let mut buf = build_props_volume_pod(&[0.5, 0.25], Some(true), VolumeCurve::Linear).unwrap();
+        let props = parse(&mut buf);
+        assert_eq!(props.volume, Some(0.5));
+        assert_eq!(props.muted, Some(true));
+        assert_eq!(props.channel_count, Some(2));
+        assert_eq!(props.channel_volumes, vec![0.5, 0.25]);
+    }
+
+    /// The value of `key` in a POD object, if it has that prop.
+    unsafe fn find_prop(pod: *mut spa_sys::spa_pod, key: u32) -> Option<*mut spa_sys::spa_pod> {
+        let obj = pod as *mut spa_sys::spa_pod_object;
+        let body = unsafe { &(*obj).body };
+        let size = unsafe { (*obj).pod.size };
+        let mut iter = unsafe { spa_sys::spa_pod_prop_first(body) };
+        while unsafe { spa_sys::spa_pod_prop_is_inside(body, size, iter) } {
+            if unsafe { (*iter).key } == key {
+                return Some(unsafe { &mut (*iter).value as *mut spa_sys::spa_pod });
+            }
+            iter = unsafe { spa_sys::spa_pod_prop_next(iter) };
+        }
+        None
+    }
+
+    #[test]
+    fn float_array_lands_in_the_channel_volumes_prop() {
+        let mut buf = build_route_volume_pod(3, 1, &[0.5, 0.25, 1.0], Some(true), true, VolumeCurve::Linear).unwrap();
+        let pod = buf.as_mut_ptr() as *mut spa_sys::spa_pod;
+
+        let props = unsafe { find_prop(pod, ROUTE_KEY_PROPS) }.unwrap();
+        let volumes = unsafe { find_prop(props, SPA_PROP_CHANNEL_VOLUMES) }.unwrap();
+        assert_eq!(unsafe { read_float_array(volumes) }, vec![0.5, 0.25, 1.0]);
+
+        // The props after the array are still read correctly.
+        let parsed = unsafe { parse_props(props) };
+        assert_eq!(parsed.muted, Some(true));
+        assert_eq!(parsed.channel_count, Some(3));
+    }
+
+    /// A Profile or EnumRoute object with an index, a name and no description.
+    fn name_only_pod(object_type: u32, param: spa::param::ParamType, direction: Option<u32>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut builder = spa::pod::builder::Builder::new(&mut buf);
+        unsafe {
+            let mut frame: MaybeUninit<spa_sys::spa_pod_frame> = MaybeUninit::uninit();
+            let (index_key, name_key) = match direction {
+                Some(_) => (ROUTE_KEY_INDEX, ROUTE_KEY_NAME),
+                None => (PROFILE_KEY_INDEX, PROFILE_KEY_NAME),
+            };
+            builder.push_object(&mut frame, object_type, param.as_raw()).unwrap();
+            builder.add_prop(index_key, 0).unwrap();
+            builder.add_int(2).unwrap();
+            if let Some(direction) = direction {
+                builder.add_prop(ROUTE_KEY_DIRECTION, 0).unwrap();
+                builder.add_id(spa::utils::Id(direction)).unwrap();
+            }
+            builder.add_prop(name_key, 0).unwrap();
+            builder.add_string("output:analog-stereo").unwrap();
+            builder.pop(&mut frame.assume_init());
+        }
+        buf
+    }
+
+    #[test]
+    fn profile_without_description_shows_its_name() {
+        let buf = name_only_pod(SPA_TYPE_OBJECT_PARAM_PROFILE, spa::param::ParamType::EnumProfile, None);
+        let profile = unsafe { parse_profile(buf.as_ptr() as *const spa_sys::spa_pod) }.unwrap();
+        assert_eq!(profile.index, 2);
+        assert_eq!(profile.description, "output:analog-stereo");
+    }
+
+    #[test]
+    fn route_without_description_shows_its_name() {
+        let buf = name_only_pod(SPA_TYPE_OBJECT_PARAM_ROUTE, spa::param::ParamType::EnumRoute, Some(1));
+        let route = unsafe { parse_enum_route(buf.as_ptr() as *const spa_sys::spa_pod) }.unwrap();
+        assert_eq!(route.index, 2);
+        assert_eq!(route.description, "output:analog-stereo");
+    }
+}
@@ -1,35 +1,300 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Instant;
 
 #[derive(Clone, Debug)]
 pub struct AudioNode {
     pub id: u32,
     pub name: String,
+    /// Identifies the node across restarts and reboots, unlike the registry `id`.
+    pub stable_id: String,
     pub description: String,
     pub volume: f32,
+    /// Volume captured when the node was muted, restored on unmute.
+    pub pre_mute_volume: Option<f32>,
     pub muted: bool,
     pub is_sink: bool,
     pub is_stream: bool,
     pub is_default: bool,
+    /// When this node became the default, if that replaced an earlier default.
+    pub default_since: Option<Instant>,
     pub media_class: String,
-    pub channel_count: u32,
+    /// `application.name` of a stream's client, used to group an app's streams.
+    pub app_name: Option<String>,
+    /// `media.role` of a stream, e.g. "Music", "Game" or "Notification".
+    pub role: Option<String>,
+    pub kind: NodeKind,
+    /// A source that loops back an output (`*.monitor`), rather than a real input.
+    pub is_monitor: bool,
+    /// Channel count from the node's Props or Route; `None` until one arrives.
+    pub channel_count: Option<u32>,
+    /// Per-channel volumes in slider units; empty until PipeWire reports them.
+    pub channel_volumes: Vec<f32>,
+    /// Channels muted on their own by zeroing their volume; same order as `channel_volumes`.
+    pub channel_muted: Vec<bool>,
+    /// Each muted channel's level from before it was muted, restored on unmute.
+    pub channel_unmute_levels: Vec<f32>,
+    /// Left/right balance from -1.0 (left only) to 1.0 (right only), from the first two channels.
+    pub balance: f32,
     pub device_id: Option<u32>,
+    /// The card's route device this node belongs to (`card.profile.device`).
+    pub profile_device: Option<u32>,
     pub target_id: Option<u32>,
     pub route_index: Option<u32>,
     pub route_device: Option<u32>,
+    /// Last stream volume we sent, used to notice apps that reset it.
+    pub volume_request: Option<VolumeRequest>,
+    /// Volumes we wrote in the last moment, newest last, each with when it was sent.
+    /// Echoes of the older ones arrive after the newer value is already shown.
+    pub sent_volumes: Vec<(f32, Instant)>,
+    /// Set when the owning application reverted a volume we applied.
+    pub volume_overridden: bool,
+    /// Whether a level meter is attached; false when capture could not be set up.
+    pub meter_available: bool,
+    /// Linear peak level of the most recent buffer the meter captured.
+    pub peak: f32,
+    /// Highest recent peak, falling slowly back towards `peak`.
+    pub peak_hold: f32,
+    /// Number of times the node entered the error state since it appeared.
+    pub error_count: u32,
+    /// The current error message while the node is in the error state.
+    pub error: Option<String>,
+    /// When `error_count` last went up, used to highlight the counter.
+    pub error_at: Option<Instant>,
+    /// When the volume or mute state last changed, from any source.
+    pub last_changed: Option<Instant>,
+    /// Whether a volume update has arrived yet; the first one is not a change.
+    pub volume_known: bool,
+    /// When we last asked PipeWire to mute, until it reports the node as muted.
+    pub mute_sent_at: Option<Instant>,
+    /// The device ignored a mute request, so muting zeroes its channel volumes instead.
+    pub ignores_mute: bool,
+    /// Currently muted through zeroed channel volumes; `pre_mute_volume` holds the level.
+    pub zero_volume_mute: bool,
+    /// The negotiated format; `None` while the node has no fixed format (e.g. suspended).
+    pub format: Option<AudioFormat>,
+    /// The latency the node requests through `node.latency`, if it sets one.
+    pub latency: Option<NodeLatency>,
+    pub state: NodeState,
+}
+
+/// Whether a node is processing audio, from its info.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NodeState {
+    /// Also covers nodes still being created.
+    #[default]
+    Idle,
+    Running,
+    /// Closed down after being idle for a while.
+    Suspended,
+    Error,
+}
+
+/// Left and right levels for a stereo node at `volume` with the given balance;
+/// the louder side stays at `volume`.
+pub fn stereo_levels(volume: f32, balance: f32) -> Vec<f32> {
+    vec![volume * (1.0 - balance.max(0.0)), volume * (1.0 + balance.min(0.0))]
+}
+
+/// A link from an output port of one node to an input port of another.
+#[derive(Clone, Debug)]
+pub struct LinkInfo {
+    pub output_node: u32,
+    pub output_port: u32,
+    pub input_node: u32,
+    pub input_port: u32,
+    pub state: LinkState,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkState {
+    /// Still being set up: negotiating a format or allocating buffers.
+    Connecting,
+    /// Set up, but the nodes aren't running.
+    Paused,
+    Active,
+    Error(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct PortInfo {
+    pub node_id: u32,
+    pub is_output: bool,
+    /// `port.name`, e.g. "playback_FL" or "monitor_FR".
+    pub name: String,
+    /// `audio.channel`, e.g. "FL"; unset on MIDI and some virtual ports.
+    pub channel: Option<String>,
+}
+
+/// What a node is, as far as its props tell; picks the icon shown next to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Speakers,
+    Headphones,
+    /// HDMI or DisplayPort output to a monitor or TV.
+    Display,
+    Microphone,
+    Browser,
+    Music,
+    Video,
+    Game,
+    Call,
+    Notification,
+    /// Any other application stream.
+    App,
+}
+
+/// How slider positions map to the linear gain PipeWire works with. Volumes are stored
+/// in slider units throughout; the curve is only applied where they meet PipeWire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeCurve {
+    /// Slider is the cube root of gain, like pavucontrol and `wpctl`.
+    #[default]
+    Cubic,
+    /// Slider is the gain itself.
+    Linear,
+    /// Slider is the square root of gain.
+    Square,
+}
+
+impl VolumeCurve {
+    pub fn to_slider(self, gain: f32) -> f32 {
+        match self {
+            VolumeCurve::Cubic => gain.cbrt(),
+            VolumeCurve::Linear => gain,
+            VolumeCurve::Square => gain.max(0.0).sqrt(),
+        }
+    }
+
+    pub fn to_gain(self, slider: f32) -> f32 {
+        match self {
+            VolumeCurve::Cubic => slider.powi(3),
+            VolumeCurve::Linear => slider,
+            VolumeCurve::Square => slider.powi(2),
+        }
+    }
+}
+
+/// Sample rate, channel count and sample format a node is running at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioFormat {
+    pub rate: u32,
+    pub channels: u32,
+    /// Short sample format name such as "float32", from `spa::sample_format_name`.
+    pub sample_format: &'static str,
+}
+
+impl AudioFormat {
+    /// e.g. "48000 Hz / stereo / float32".
+    pub fn summary(&self) -> String {
+        let channels = match self.channels {
+            1 => "mono".to_string(),
+            2 => "stereo".to_string(),
+            n => format!("{} ch", n),
+        };
+        format!("{} Hz / {} / {}", self.rate, channels, self.sample_format)
+    }
+}
+
+/// A node's requested quantum (buffer size in samples) at its rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeLatency {
+    pub quantum: u32,
+    pub rate: u32,
+}
+
+impl NodeLatency {
+    pub fn latency_ns(&self) -> u64 {
+        self.quantum as u64 * 1_000_000_000 / self.rate as u64
+    }
+
+    /// e.g. "1024/48000 (21.3 ms)".
+    pub fn summary(&self) -> String {
+        format!("{}/{} ({:.1} ms)", self.quantum, self.rate, self.latency_ns() as f64 / 1_000_000.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct VolumeRequest {
+    pub volume: f32,
+    pub sent_at: Instant,
+    /// Whether PipeWire has reported our requested value back yet.
+    pub confirmed: bool,
 }
 
 #[derive(Clone, Debug)]
 pub struct Card {
     pub id: u32,
+    /// The `device.name`, which keys the card's saved state in WirePlumber.
+    pub name: String,
     pub description: String,
     pub profiles: Vec<Profile>,
     pub active_profile_index: Option<u32>,
+    pub routes: Vec<Route>,
+    /// The route in use on each route device (`card.profile.device`), from the Route params.
+    pub active_routes: HashMap<u32, ActiveRoute>,
+}
+
+impl Card {
+    /// The highest-priority available profile, which is what the session manager
+    /// would pick for this card by default.
+    pub fn recommended_profile(&self) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .filter(|p| p.available)
+            .max_by_key(|p| p.priority)
+    }
+
+    /// Whether the active profile leaves the card's nodes unusable: the "Off" profile
+    /// (always index 0 for ALSA and Bluetooth cards) or one marked unavailable.
+    pub fn is_inactive(&self) -> bool {
+        let Some(index) = self.active_profile_index else { return false };
+        index == 0 || self.profiles.iter().any(|p| p.index == index && !p.available)
+    }
+
+    /// Routes (ports) the card can switch `node` between under the active profile.
+    pub fn ports_for(&self, node: &AudioNode) -> Vec<&Route> {
+        let direction = if node.is_sink { 1 } else { 0 };
+        self.routes
+            .iter()
+            .filter(|r| r.direction == direction)
+            .filter(|r| self.active_profile_index.is_none_or(|p| r.profiles.contains(&p)))
+            .filter(|r| node.profile_device.is_none_or(|d| r.devices.is_empty() || r.devices.contains(&d)))
+            .collect()
+    }
+}
+
+/// A route (port) the card can offer, from its EnumRoute list.
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub index: u32,
+    /// 0 = input, 1 = output, as in SPA.
+    pub direction: u32,
+    pub description: String,
+    /// False when the port is known to be unplugged.
+    pub available: bool,
+    /// Indices of the profiles that provide this route.
+    pub profiles: Vec<u32>,
+    /// Route devices (`card.profile.device`) the route can be selected on.
+    pub devices: Vec<u32>,
+}
+
+/// A route currently selected on one of the card's route devices.
+#[derive(Clone, Copy, Debug)]
+pub struct ActiveRoute {
+    pub index: u32,
+    /// 0 = input, 1 = output, as in SPA.
+    pub direction: u32,
+    /// Channels of the route's volume, once a Route param has reported them.
+    pub channels: Option<u32>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Profile {
     pub index: u32,
     pub description: String,
+    pub priority: u32,
     pub available: bool,
 }
 
@@ -38,8 +303,44 @@ pub struct AppState {
     pub cards: HashMap<u32, Card>,
     pub default_sink_name: Option<String>,
     pub default_source_name: Option<String>,
-    pub show_volume_meters: bool,
-    pub hide_unavailable_profiles: bool,
+    /// The user's chosen defaults, which differ from the active ones when they fell back.
+    pub configured_sink_name: Option<String>,
+    pub configured_source_name: Option<String>,
+    /// These six mirror the same-named `Settings` for the PipeWire thread; see `Settings::apply_to`.
+    pub auto_unmute_on_call: bool,
+    pub call_apps: Vec<String>,
+    pub volume_feedback_sound: bool,
+    pub volume_notifications: bool,
+    pub persist_changes: bool,
+    pub persist_overrides: HashMap<String, bool>,
+    /// Channel counts from the config that replace what a node reports, keyed by node name.
+    pub channel_overrides: HashMap<String, u32>,
+    /// Initial volume (slider units) for new streams, keyed by lowercase `media.role`.
+    pub role_volumes: HashMap<String, f32>,
+    /// Volume and mute last set from Copper for each application's playback streams.
+    pub app_volumes: HashMap<String, (f32, bool)>,
+    /// Groups of devices, by stable id, whose volumes move together.
+    pub volume_groups: Vec<Vec<String>>,
+    /// Mapping between slider units and PipeWire's linear volumes.
+    pub volume_curve: VolumeCurve,
+    /// False while the PipeWire daemon can't be reached and Copper is retrying.
+    pub connected: bool,
+    /// Set once the first full snapshot of nodes, cards and defaults has arrived.
+    pub synced: bool,
+    /// `clock.force-rate` from the settings metadata, when set.
+    pub forced_rate: Option<u32>,
+    /// `clock.force-quantum` from the settings metadata, when set.
+    pub forced_quantum: Option<u32>,
+    /// Whether the window draws level meters; meter streams only run while it does.
+    pub meters_shown: bool,
+    /// PipeWire instance to connect to instead of the default, from `--remote`.
+    pub remote: Option<String>,
+    /// Failures from the PipeWire thread, shown as toasts until dismissed; oldest first.
+    pub messages: Vec<String>,
+    /// Connections between ports, by link id.
+    pub links: HashMap<u32, LinkInfo>,
+    /// Ports by id, to name the ends of links.
+    pub ports: HashMap<u32, PortInfo>,
 }
 
 impl AppState {
@@ -49,10 +350,137 @@ impl AppState {
             cards: HashMap::new(),
             default_sink_name: None,
             default_source_name: None,
-            show_volume_meters: true,
-            hide_unavailable_profiles: false,
+            configured_sink_name: None,
+            configured_source_name: None,
+            auto_unmute_on_call: false,
+            call_apps: Vec::new(),
+            channel_overrides: HashMap::new(),
+            role_volumes: HashMap::new(),
+            volume_feedback_sound: false,
+            volume_notifications: false,
+            persist_changes: true,
+            persist_overrides: HashMap::new(),
+            volume_groups: Vec::new(),
+            app_volumes: HashMap::new(),
+            volume_curve: VolumeCurve::default(),
+            connected: true,
+            synced: false,
+            forced_rate: None,
+            forced_quantum: None,
+            meters_shown: true,
+            remote: None,
+            messages: Vec::new(),
+            links: HashMap::new(),
+            ports: HashMap::new(),
+        }
+    }
+
+    /// Look up a node by its `node.name`, preferring devices over streams of the same name.
+    pub fn find_node_by_name(&self, name: &str) -> Option<&AudioNode> {
+        self.nodes
+            .values()
+            .find(|n| !n.is_stream && n.name == name)
+            .or_else(|| self.nodes.values().find(|n| n.name == name))
+    }
+
+    pub fn find_default_sink(&self) -> Option<&AudioNode> {
+        let name = self.default_sink_name.as_deref()?;
+        self.nodes.values().find(|n| n.is_sink && !n.is_stream && n.name == name)
+    }
+
+    /// The default source. This may be a sink when the default is set to record its monitor.
+    pub fn find_default_source(&self) -> Option<&AudioNode> {
+        let name = self.default_source_name.as_deref()?;
+        self.nodes
+            .values()
+            .find(|n| !n.is_sink && !n.is_stream && n.name == name)
+            .or_else(|| self.nodes.values().find(|n| n.is_sink && !n.is_stream && n.name == name))
+    }
+
+    /// Whether volume changes on this node should be saved by the session manager.
+    pub fn persist_for(&self, node: &AudioNode) -> bool {
+        self.persist_overrides.get(&node.stable_id).copied().unwrap_or(self.persist_changes)
+    }
+
+    pub fn find_node_by_stable_id(&self, stable_id: &str) -> Option<&AudioNode> {
+        self.nodes.values().find(|n| n.stable_id == stable_id)
+    }
+
+    /// The other members of the node's volume group that are present right now.
+    pub fn linked_nodes(&self, node: &AudioNode) -> Vec<&AudioNode> {
+        let Some(group) = self.volume_groups.iter().find(|g| g.contains(&node.stable_id)) else { return Vec::new() };
+        group
+            .iter()
+            .filter(|id| **id != node.stable_id)
+            .filter_map(|id| self.find_node_by_stable_id(id))
+            .collect()
+    }
+
+    /// Put two devices in one volume group, merging their groups, or take `other` out of
+    /// `stable_id`'s group. A group left with a single member is dropped.
+    pub fn set_volume_link(&mut self, stable_id: &str, other: &str, linked: bool) {
+        let group_of = |groups: &[Vec<String>], id: &str| groups.iter().position(|g| g.iter().any(|m| m == id));
+
+        if linked {
+            match (group_of(&self.volume_groups, stable_id), group_of(&self.volume_groups, other)) {
+                (Some(a), Some(b)) if a == b => {}
+                (Some(a), Some(b)) => {
+                    let merged = self.volume_groups.remove(b);
+                    let a = if b < a { a - 1 } else { a };
+                    self.volume_groups[a].extend(merged);
+                }
+                (Some(a), None) => self.volume_groups[a].push(other.to_string()),
+                (None, Some(b)) => self.volume_groups[b].push(stable_id.to_string()),
+                (None, None) => self.volume_groups.push(vec![stable_id.to_string(), other.to_string()]),
+            }
+        } else if let Some(a) = group_of(&self.volume_groups, stable_id) {
+            self.volume_groups[a].retain(|m| m != other);
+            if self.volume_groups[a].len() < 2 {
+                self.volume_groups.remove(a);
+            }
         }
     }
+
+    /// The default device that switching `card_id` to `profile_index` would take away,
+    /// because no route of the new profile serves the default's direction.
+    pub fn default_lost_by_profile(&self, card_id: u32, profile_index: u32) -> Option<&AudioNode> {
+        let card = self.cards.get(&card_id)?;
+        self.nodes.values().find(|node| {
+            if node.is_stream || !node.is_default || node.device_id != Some(card_id) {
+                return false;
+            }
+            if profile_index == 0 {
+                // The "Off" profile never provides routes.
+                return true;
+            }
+
+            let direction = if node.is_sink { 1 } else { 0 };
+            !card.routes.is_empty()
+                && !card
+                    .routes
+                    .iter()
+                    .any(|r| r.direction == direction && r.profiles.contains(&profile_index))
+        })
+    }
+
+    /// Whether a device node belongs to a card whose active profile is off or unavailable,
+    /// meaning it may be a leftover that can't actually be controlled.
+    pub fn is_node_inactive(&self, node: &AudioNode) -> bool {
+        !node.is_stream
+            && node
+                .device_id
+                .and_then(|id| self.cards.get(&id))
+                .is_some_and(Card::is_inactive)
+    }
+
+    /// Whether a device node's current port reports nothing plugged into it.
+    pub fn is_port_unplugged(&self, node: &AudioNode) -> bool {
+        let Some(route_index) = node.route_index else { return false };
+        node.device_id
+            .and_then(|id| self.cards.get(&id))
+            .and_then(|card| card.routes.iter().find(|r| r.index == route_index))
+            .is_some_and(|route| !route.available)
+    }
 }
 
 impl Default for AppState {
@@ -61,10 +489,203 @@ impl Default for AppState {
     }
 }
 
+/// Preferences the UI owns directly, so toggling them never touches the state shared
+/// with the PipeWire thread; the few that thread acts on are copied over by `apply_to`.
+/// Saved with the session; flags missing from the saved file keep their defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub show_volume_meters: bool,
+    /// Also hides ports that are known to be unplugged.
+    pub hide_unavailable_profiles: bool,
+    /// Pop up a small on-screen display when volume changes via a hotkey.
+    pub show_volume_osd: bool,
+    /// Show extra per-node diagnostics such as error counts.
+    pub developer_mode: bool,
+    /// Disable every control that would change the audio setup. Not saved: every launch
+    /// starts unlocked.
+    #[serde(skip)]
+    pub locked: bool,
+    /// Ask before a profile change that would remove the current default device.
+    pub confirm_profile_changes: bool,
+    /// Hide node tabs that have nothing to show.
+    pub hide_empty_tabs: bool,
+    /// Let volume sliders go up to 150%.
+    pub allow_overamplification: bool,
+    /// Show and edit volumes in decibels rather than percent.
+    pub volume_in_db: bool,
+    /// Make the percent slider travel in dB while it still shows percent.
+    pub log_volume_slider: bool,
+    /// Closing the window hides it to the tray icon instead of quitting.
+    pub close_to_tray: bool,
+    /// Show each output as a collapsible group holding the streams playing on it.
+    pub nest_streams: bool,
+    /// Combine an application's playback streams into one card with a shared slider.
+    pub group_by_app: bool,
+    /// Leave monitor sources out of the Inputs tab.
+    pub hide_monitors: bool,
+    /// Draw each node as a single row with just mute, default and volume.
+    pub compact: bool,
+    /// Volume change per scroll notch over the tray icon, in slider units.
+    pub tray_scroll_step: f32,
+    /// Unmute the default source when a communication app starts recording.
+    pub auto_unmute_on_call: bool,
+    /// Application names (matched case-insensitively as substrings) treated as call apps.
+    pub call_apps: Vec<String>,
+    /// Play a short sound on an output after its volume is changed from Copper.
+    pub volume_feedback_sound: bool,
+    /// Show a desktop notification after the default output's volume is changed from Copper.
+    pub volume_notifications: bool,
+    /// Ask the session manager to remember device volume changes across restarts.
+    pub persist_changes: bool,
+    /// Per-device exceptions to `persist_changes`, keyed by stable id.
+    pub persist_overrides: HashMap<String, bool>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            show_volume_meters: true,
+            hide_unavailable_profiles: false,
+            show_volume_osd: false,
+            developer_mode: false,
+            locked: false,
+            confirm_profile_changes: true,
+            hide_empty_tabs: false,
+            allow_overamplification: false,
+            volume_in_db: false,
+            log_volume_slider: false,
+            close_to_tray: false,
+            nest_streams: false,
+            group_by_app: true,
+            hide_monitors: false,
+            compact: false,
+            tray_scroll_step: 0.05,
+            auto_unmute_on_call: false,
+            call_apps: ["zoom", "teams", "discord", "skype", "slack", "webex", "jitsi"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            volume_feedback_sound: false,
+            volume_notifications: false,
+            persist_changes: true,
+            persist_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Copy the settings the PipeWire thread acts on into the shared state.
+    pub fn apply_to(&self, state: &mut AppState) {
+        state.meters_shown = self.show_volume_meters;
+        state.auto_unmute_on_call = self.auto_unmute_on_call;
+        if state.call_apps != self.call_apps {
+            state.call_apps = self.call_apps.clone();
+        }
+        state.volume_feedback_sound = self.volume_feedback_sound;
+        state.volume_notifications = self.volume_notifications;
+        state.persist_changes = self.persist_changes;
+        if state.persist_overrides != self.persist_overrides {
+            state.persist_overrides = self.persist_overrides.clone();
+        }
+    }
+
+    /// Set whether a device's volume is remembered, dropping the override when it
+    /// matches the global setting.
+    pub fn set_persist_override(&mut self, stable_id: &str, persist: bool) {
+        if persist == self.persist_changes {
+            self.persist_overrides.remove(stable_id);
+        } else {
+            self.persist_overrides.insert(stable_id.to_string(), persist);
+        }
+    }
+
+    /// Replace the call apps from a comma-separated list.
+    pub fn set_call_apps(&mut self, list: &str) {
+        self.call_apps = list
+            .split(',')
+            .map(|app| app.trim().to_string())
+            .filter(|app| !app.is_empty())
+            .collect();
+    }
+}
+
 pub enum PwCommand {
     SetVolume(u32, f32),
     SetMute(u32, bool),
+    /// Set each channel's volume (slider units) individually.
+    SetChannelVolumes(u32, Vec<f32>),
     SetDefault(u32),
+    /// Send a stream to another device, or back to following the default with `None`.
+    MoveStream(u32, Option<u32>),
     SetCardProfile(u32, u32),
+    /// Drop every proxy and fetch the whole graph from PipeWire again.
+    Rescan,
+    /// Mute or unmute single channels of a node: the new mute state of every channel.
+    SetChannelMutes(u32, Vec<bool>),
+    /// Switch a device node to another route (port) of its card, by route index.
+    SetPort(u32, u32),
+    /// Force the graph's sample rate; 0 lets PipeWire pick it again.
+    SetForcedRate(u32),
+    /// Force the graph's quantum (buffer size in samples); 0 lets PipeWire pick it again.
+    SetForcedQuantum(u32),
+    /// Put a card's active routes back to the session manager's default volume and save that.
+    ResetRouteVolumes(u32),
+    /// Link every output port of the first node to the matching input port of the second.
+    CreateLink(u32, u32),
+    /// Remove a link, by link id.
+    DestroyLink(u32),
     Quit,
 }
+
+#[cfg(test)]
+impl AudioNode {
+    /// A device sink with nothing known about it yet, for tests.
+    pub fn for_test(id: u32, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            stable_id: name.to_string(),
+            description: name.to_string(),
+            volume: 1.0,
+            pre_mute_volume: None,
+            muted: false,
+            is_sink: true,
+            is_stream: false,
+            is_default: false,
+            default_since: None,
+            media_class: "Audio/Sink".to_string(),
+            app_name: None,
+            role: None,
+            kind: NodeKind::Speakers,
+            is_monitor: false,
+            channel_count: None,
+            channel_volumes: Vec::new(),
+            channel_muted: Vec::new(),
+            channel_unmute_levels: Vec::new(),
+            balance: 0.0,
+            device_id: None,
+            profile_device: None,
+            target_id: None,
+            route_index: None,
+            route_device: None,
+            volume_request: None,
+            sent_volumes: Vec::new(),
+            volume_overridden: false,
+            meter_available: false,
+            peak: 0.0,
+            peak_hold: 0.0,
+            error_count: 0,
+            error: None,
+            error_at: None,
+            last_changed: None,
+            volume_known: false,
+            mute_sent_at: None,
+            ignores_mute: false,
+            zero_volume_mute: false,
+            format: None,
+            latency: None,
+            state: NodeState::Idle,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! System tray icon (StatusNotifierItem) with the default output's volume and mute.
+
+use crate::state::{AppState, PwCommand};
+use crossbeam_channel::Sender;
+use eframe::egui;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// What the tray shows, pushed from the UI whenever it changes.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct TrayStatus {
+    /// The default output's volume in slider units, if there is a default output.
+    pub volume: Option<f32>,
+    pub muted: bool,
+    /// Top of the volume range, which scrolling on the icon stays within.
+    pub max_volume: f32,
+    /// Mirrors the UI lock, which also makes the tray's controls read-only.
+    pub locked: bool,
+    /// Volume change per scroll step over the icon, in slider units.
+    pub scroll_step: f32,
+}
+
+struct CopperTray {
+    state: Arc<Mutex<AppState>>,
+    tx: Sender<PwCommand>,
+    ctx: egui::Context,
+    status: TrayStatus,
+    online: Arc<AtomicBool>,
+    quit_requested: Arc<AtomicBool>,
+}
+
+impl CopperTray {
+    fn toggle_mute(&mut self) {
+        if self.status.locked {
+            return;
+        }
+        let Some(sink_id) = self.state.lock().find_default_sink().map(|n| n.id) else { return };
+        let _ = self.tx.send(PwCommand::SetMute(sink_id, !self.status.muted));
+    }
+
+    fn show_window(&mut self) {
+        self.ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+        self.ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        self.ctx.request_repaint();
+    }
+
+    /// Let the UI quit, so it can save its state first.
+    fn quit(&mut self) {
+        self.quit_requested.store(true, Ordering::Relaxed);
+        self.show_window();
+    }
+}
+
+impl ksni::Tray for CopperTray {
+    fn id(&self) -> String {
+        "copper".to_string()
+    }
+
+    fn title(&self) -> String {
+        "Copper".to_string()
+    }
+
+    fn icon_name(&self) -> String {
+        match self.status.volume {
+            _ if self.status.muted => "audio-volume-muted",
+            Some(v) if v > 0.66 => "audio-volume-high",
+            Some(v) if v > 0.33 => "audio-volume-medium",
+            _ => "audio-volume-low",
+        }
+        .to_string()
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        let description = match self.status.volume {
+            Some(_) if self.status.muted => "Muted".to_string(),
+            Some(v) => format!("Volume {:.0}%", v * 100.0),
+            None => "No output device".to_string(),
+        };
+        ksni::ToolTip {
+            title: "Copper".to_string(),
+            description,
+            ..Default::default()
+        }
+    }
+
+    /// Left click opens the window.
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.show_window();
+    }
+
+    /// Middle click toggles mute.
+    fn secondary_activate(&mut self, _x: i32, _y: i32) {
+        self.toggle_mute();
+    }
+
+    fn scroll(&mut self, delta: i32, dir: &str) {
+        if dir != "vertical" || self.status.locked {
+            return;
+        }
+        let Some(volume) = self.status.volume else { return };
+        let Some(sink_id) = self.state.lock().find_default_sink().map(|n| n.id) else { return };
+        // Hosts report scrolling up as a negative delta.
+        let volume = (volume - delta.signum() as f32 * self.status.scroll_step).clamp(0.0, self.status.max_volume);
+        let _ = self.tx.send(PwCommand::SetVolume(sink_id, volume));
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        use ksni::menu::{CheckmarkItem, StandardItem};
+
+        let volume_label = match self.status.volume {
+            Some(v) => format!("Volume: {:.0}%", v * 100.0),
+            None => "No output device".to_string(),
+        };
+        vec![
+            StandardItem {
+                label: volume_label,
+                enabled: false,
+                ..Default::default()
+            }
+            .into(),
+            CheckmarkItem {
+                label: "Mute".to_string(),
+                enabled: self.status.volume.is_some() && !self.status.locked,
+                checked: self.status.muted,
+                activate: Box::new(|tray: &mut Self| tray.toggle_mute()),
+                ..Default::default()
+            }
+            .into(),
+            ksni::MenuItem::Separator,
+            StandardItem {
+                label: "Show Copper".to_string(),
+                activate: Box::new(|tray: &mut Self| tray.show_window()),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: "Quit".to_string(),
+                activate: Box::new(|tray: &mut Self| tray.quit()),
+                ..Default::default()
+            }
+            .into(),
+        ]
+    }
+
+    fn watcher_online(&self) {
+        self.online.store(true, Ordering::Relaxed);
+    }
+
+    fn watcher_offine(&self) -> bool {
+        // Keep running so the icon comes back if the tray host restarts.
+        self.online.store(false, Ordering::Relaxed);
+        true
+    }
+}
+
+/// The UI's handle on the tray icon, which runs on its own thread.
+pub struct Tray {
+    handle: ksni::Handle<CopperTray>,
+    status: TrayStatus,
+    online: Arc<AtomicBool>,
+    quit_requested: Arc<AtomicBool>,
+}
+
+impl Tray {
+    pub fn spawn(state: Arc<Mutex<AppState>>, tx: Sender<PwCommand>, ctx: egui::Context) -> Self {
+        let online = Arc::new(AtomicBool::new(false));
+        let quit_requested = Arc::new(AtomicBool::new(false));
+        let service = ksni::TrayService::new(CopperTray {
+            state,
+            tx,
+            ctx,
+            status: TrayStatus::default(),
+            online: online.clone(),
+            quit_requested: quit_requested.clone(),
+        });
+        let handle = service.handle();
+        service.spawn();
+
+        Self {
+            handle,
+            status: TrayStatus::default(),
+            online,
+            quit_requested,
+        }
+    }
+
+    /// Whether a tray host is showing the icon, so the window can be restored from it.
+    pub fn is_available(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    /// Whether Quit was chosen from the tray menu.
+    pub fn quit_requested(&self) -> bool {
+        self.quit_requested.load(Ordering::Relaxed)
+    }
+
+    pub fn set_status(&mut self, status: TrayStatus) {
+        if status != self.status {
+            self.status = status;
+            self.handle.update(|tray| tray.status = status);
+        }
+    }
+}
@@ -1,44 +1,327 @@
-use crate::state::{AppState, AudioNode, PwCommand};
+use crate::config::{self, DefaultTab, Preset, Presets, Session, Theme, WindowGeometry};
+use crate::state::{
+    AppState, AudioNode, Card, LinkInfo, LinkState, NodeKind, NodeState, PwCommand, Settings, VolumeCurve, stereo_levels,
+};
+use crate::tray::{Tray, TrayStatus};
 use crossbeam_channel::Sender;
 use eframe::egui;
 use parking_lot::Mutex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The accent color used until the user picks another.
+const DEFAULT_ACCENT: egui::Color32 = egui::Color32::from_rgb(212, 115, 49);
+
+/// Volume change applied by the keyboard shortcuts, in slider units.
+const KEYBOARD_VOLUME_STEP: f32 = 0.05;
+/// Highest volume, in slider units, when boosting past 100% is allowed.
+const MAX_BOOSTED_VOLUME: f32 = 1.5;
+/// Volume steps for the focused node: arrow keys and Page Up/Down.
+const FOCUS_VOLUME_STEP: f32 = 0.01;
+const FOCUS_VOLUME_PAGE: f32 = 0.05;
+/// Volume change per mouse wheel notch over a node.
+const SCROLL_VOLUME_STEP: f32 = 0.02;
+/// Pixel-precise (touchpad) scrolling this far counts as one wheel notch.
+const SCROLL_POINTS_PER_NOTCH: f32 = 50.0;
+/// Bottom of the dB scale; the slider treats it as silence.
+const MIN_DB: f32 = -60.0;
+
+/// How long the volume OSD stays up, including its fade-out.
+const OSD_DURATION: Duration = Duration::from_millis(1500);
+const OSD_FADE: Duration = Duration::from_millis(400);
+/// How long a newly selected default device stays highlighted.
+const DEFAULT_FLASH: Duration = Duration::from_millis(1200);
+/// How long a node's error counter stays highlighted after it goes up.
+const ERROR_HIGHLIGHT: Duration = Duration::from_secs(3);
+/// How long a node stays highlighted after its volume or mute state changes.
+const CHANGE_HIGHLIGHT: Duration = Duration::from_secs(1);
+
+/// Cards with more profiles than this get a filter box in their profile selector.
+const PROFILE_FILTER_MIN: usize = 8;
+
+/// How fast a level meter falls back after a peak, in slider units per second.
+const METER_DECAY: f32 = 1.5;
+const METER_HEIGHT: f32 = 4.0;
+/// Frame interval while a meter is falling back, about 30 frames per second.
+const METER_FRAME: Duration = Duration::from_millis(33);
+
+type NodeFilter = fn(&AudioNode) -> bool;
 
 pub struct CopperApp {
     state: Arc<Mutex<AppState>>,
     tx: Sender<PwCommand>,
+    settings: Settings,
     current_tab: Tab,
+    /// The clicked or keyboard-focused node, which the focus shortcuts act on.
+    selected_node: Option<u32>,
+    /// Nodes in the order the last frame drew them, for moving focus with Tab.
+    node_order: Vec<u32>,
+    /// Set when focus moved by keyboard, so the newly focused node is scrolled into view.
+    focus_moved: bool,
+    /// Wheel movement over a node that hasn't added up to a whole notch yet.
+    scroll_notches: f32,
+    volume_entry: Option<VolumeEntry>,
+    call_apps_text: String,
+    osd: Option<Osd>,
+    /// The default switch we already scrolled to, so it happens once per switch.
+    scrolled_to_default: Option<Instant>,
+    confirm: Option<Confirm>,
+    switcher: Option<Switcher>,
+    /// A per-device persistence change from a context menu, applied once the
+    /// state is no longer borrowed for rendering.
+    persist_toggle: Option<(String, bool)>,
+    /// A volume link change from a context menu: two stable ids and whether they are linked,
+    /// applied like `persist_toggle`.
+    link_toggle: Option<(String, String, bool)>,
+    /// Text typed into the open profile selector, and the card it belongs to.
+    profile_filter: (u32, String),
+    /// Nodes whose per-channel sliders move together.
+    channels_locked: HashSet<u32>,
+    /// Saved volume presets, and the name typed or picked in the Presets section.
+    presets: Presets,
+    preset_name: String,
+    /// Entries of the last applied preset whose nodes weren't there yet, applied when they appear.
+    pending_preset: Preset,
+    /// Which `media.role`s the Playback tab shows.
+    role_filter: RoleFilter,
+    /// The nodes picked in the Links tab's Connect row.
+    link_source: Option<u32>,
+    link_target: Option<u32>,
+    /// Displayed meter level per node in slider units, decaying towards the live peak.
+    meter_levels: HashMap<u32, f32>,
+    /// The window's current geometry, saved with the open tab when Copper closes.
+    window: Option<WindowGeometry>,
+    tray: Tray,
+    /// Color for highlights and headings, chosen in the settings.
+    accent: egui::Color32,
+    theme: Theme,
+    /// Window level and decorations, saved with the session.
+    always_on_top: bool,
+    borderless: bool,
+    /// From the config; fixed for the run since stored volumes are in its slider units.
+    volume_curve: VolumeCurve,
+    /// Set once Copper is really quitting, so the close isn't turned into hiding to the tray.
+    quitting: bool,
+}
+
+/// The quick output switcher popup: a filter query and the highlighted match.
+#[derive(Default)]
+struct Switcher {
+    query: String,
+    highlighted: usize,
+}
+
+/// A command waiting for the user to confirm it in a dialog.
+struct Confirm {
+    title: &'static str,
+    message: String,
+    action: &'static str,
+    command: PwCommand,
+}
+
+/// A volume change currently shown in the OSD viewport.
+#[derive(Clone, Copy)]
+struct Osd {
+    node_id: u32,
+    shown_at: Instant,
+}
+
+/// Inline editor for typing an exact volume on the selected node.
+struct VolumeEntry {
+    node_id: u32,
+    text: String,
+    request_focus: bool,
+}
+
+/// The Playback tab's role dropdown: every stream, only one role, or all but one role.
+#[derive(Clone, PartialEq)]
+enum RoleFilter {
+    All,
+    Only(String),
+    Hide(String),
 }
 
-#[derive(PartialEq)]
+impl RoleFilter {
+    fn matches(&self, node: &AudioNode) -> bool {
+        match self {
+            RoleFilter::All => true,
+            RoleFilter::Only(role) => node.role.as_ref() == Some(role),
+            RoleFilter::Hide(role) => node.role.as_ref() != Some(role),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            RoleFilter::All => "All roles".to_string(),
+            RoleFilter::Only(role) => format!("Only {}", role),
+            RoleFilter::Hide(role) => format!("Hide {}", role),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 enum Tab {
     Outputs,
     Inputs,
     Playback,
     Recording,
     Configuration,
+    All,
+    Links,
+}
+
+impl Tab {
+    const ALL: [Tab; 7] = [
+        Tab::Outputs,
+        Tab::Inputs,
+        Tab::Playback,
+        Tab::Recording,
+        Tab::Configuration,
+        Tab::All,
+        Tab::Links,
+    ];
+
+    /// The tab to open at startup; `Remember` reopens `last_tab`, or Outputs if it is unknown.
+    fn from_config(default_tab: DefaultTab, last_tab: Option<&str>) -> Self {
+        match default_tab {
+            DefaultTab::Remember => last_tab.and_then(Tab::from_name).unwrap_or(Tab::Outputs),
+            DefaultTab::Outputs => Tab::Outputs,
+            DefaultTab::Inputs => Tab::Inputs,
+            DefaultTab::Playback => Tab::Playback,
+            DefaultTab::Recording => Tab::Recording,
+            DefaultTab::Configuration => Tab::Configuration,
+        }
+    }
+
+    /// Stable name used when saving the tab, independent of the enum's order.
+    fn name(self) -> &'static str {
+        match self {
+            Tab::Outputs => "Outputs",
+            Tab::Inputs => "Inputs",
+            Tab::Playback => "Playback",
+            Tab::Recording => "Recording",
+            Tab::Configuration => "Configuration",
+            Tab::All => "All",
+            Tab::Links => "Links",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Tab::ALL.into_iter().find(|tab| tab.name() == name)
+    }
 }
 
 impl CopperApp {
-    pub fn new(state: Arc<Mutex<AppState>>, tx: Sender<PwCommand>) -> Self {
+    pub fn new(
+        state: Arc<Mutex<AppState>>,
+        tx: Sender<PwCommand>,
+        default_tab: DefaultTab,
+        session: Session,
+        tray: Tray,
+    ) -> Self {
+        let call_apps_text = session.settings.call_apps.join(", ");
+        let volume_curve = state.lock().volume_curve;
+
         Self {
             state,
             tx,
-            current_tab: Tab::Outputs,
+            settings: session.settings,
+            current_tab: Tab::from_config(default_tab, session.tab.as_deref()),
+            selected_node: None,
+            node_order: Vec::new(),
+            focus_moved: false,
+            scroll_notches: 0.0,
+            volume_entry: None,
+            call_apps_text,
+            osd: None,
+            scrolled_to_default: None,
+            confirm: None,
+            switcher: None,
+            persist_toggle: None,
+            link_toggle: None,
+            profile_filter: (0, String::new()),
+            channels_locked: HashSet::new(),
+            presets: config::load_presets(),
+            preset_name: String::new(),
+            pending_preset: Preset::new(),
+            role_filter: RoleFilter::All,
+            link_source: None,
+            link_target: None,
+            meter_levels: HashMap::new(),
+            window: None,
+            tray,
+            accent: session.accent_color.map_or(DEFAULT_ACCENT, |[r, g, b]| egui::Color32::from_rgb(r, g, b)),
+            theme: session.theme,
+            always_on_top: session.always_on_top,
+            borderless: session.borderless,
+            volume_curve,
+            quitting: false,
         }
     }
 
-    fn render_node(&self, ui: &mut egui::Ui, node: &AudioNode, state: &AppState) {
+    fn render_node(&mut self, ui: &mut egui::Ui, node: &AudioNode, state: &AppState) {
         let mut frame = egui::Frame::group(ui.style());
         if node.is_default {
             frame = frame.fill(ui.visuals().selection.bg_fill.linear_multiply(0.1));
             frame = frame.stroke(egui::Stroke::new(1.0, ui.visuals().selection.bg_fill));
         }
+        if self.selected_node == Some(node.id) {
+            frame = frame.stroke(egui::Stroke::new(1.5, ui.visuals().strong_text_color()));
+        }
+
+        if let Some(elapsed) = node.last_changed.map(|at| at.elapsed()).filter(|e| *e < CHANGE_HIGHLIGHT) {
+            let strength = 1.0 - elapsed.as_secs_f32() / CHANGE_HIGHLIGHT.as_secs_f32();
+            frame = frame.stroke(egui::Stroke::new(1.0 + strength, self.accent.linear_multiply(strength)));
+            ui.ctx().request_repaint();
+        }
+
+        let flash = node
+            .default_since
+            .filter(|since| node.is_default && since.elapsed() < DEFAULT_FLASH);
+        if let Some(since) = flash {
+            let strength = 1.0 - since.elapsed().as_secs_f32() / DEFAULT_FLASH.as_secs_f32();
+            frame = frame.fill(ui.visuals().selection.bg_fill.linear_multiply(0.1 + 0.4 * strength));
+            ui.ctx().request_repaint();
+        }
+
+        let inactive = state.is_node_inactive(node);
+        if inactive {
+            frame = frame.multiply_with_opacity(0.6);
+        }
+
+        if self.settings.compact {
+            frame = frame.inner_margin(egui::Margin::symmetric(4, 1));
+        }
 
-        frame.show(ui, |ui| {
+        let mut meter_scrolled = false;
+        let response = frame.show(ui, |ui| {
             ui.set_min_width(ui.available_width());
+            if self.settings.compact {
+                ui.horizontal(|ui| {
+                    state_dot(ui, node.state);
+                    ui.label(node_icon(node.kind));
+                    ui.add_enabled_ui(!self.settings.locked && !inactive, |ui| {
+                        ui.horizontal(|ui| self.render_volume_controls(ui, node, ""))
+                    });
+                    if node.volume_overridden {
+                        ui.label(egui::RichText::new("⚠").color(ui.visuals().warn_fg_color))
+                            .on_hover_text("This application resets its own volume; changes made here may not stick");
+                    }
+                    ui.add(egui::Label::new(egui::RichText::new(&node.description).strong()).truncate())
+                        .on_hover_text(&node.name);
+                });
+                if self.settings.show_volume_meters && node.meter_available {
+                    meter_scrolled = self.render_meter(ui, node);
+                }
+                self.render_volume_entry(ui, node);
+                return;
+            }
+
             ui.vertical(|ui| {
                 ui.horizontal(|ui| {
+                    state_dot(ui, node.state);
+                    ui.label(node_icon(node.kind));
                     ui.add(
                         egui::Label::new(egui::RichText::new(&node.description).strong()).truncate(),
                     );
@@ -48,15 +331,11 @@ impl CopperApp {
                             state.nodes.get(&target_id)
                         } else {
                             // If no target_id, try to find the default node
-                            let default_name = if node.is_sink {
-                                state.default_sink_name.as_ref()
+                            if node.is_sink {
+                                state.find_default_sink()
                             } else {
-                                state.default_source_name.as_ref()
-                            };
-
-                            default_name.and_then(|name| {
-                                state.nodes.values().find(|n| n.name == *name)
-                            })
+                                state.find_default_source()
+                            }
                         };
 
                         if let Some(target) = target_node {
@@ -69,65 +348,1030 @@ impl CopperApp {
                             };
                             ui.label(egui::RichText::new(format!(" {} {}", prefix, target.description)).small().weak());
                         }
+
+                        if node.volume_overridden {
+                            ui.label(egui::RichText::new("⚠").color(ui.visuals().warn_fg_color))
+                                .on_hover_text("This application resets its own volume; changes made here may not stick");
+                        }
                     }
                 });
 
-                ui.add(
-                    egui::Label::new(egui::RichText::new(&node.name).small().weak()).truncate(),
-                );
-
                 ui.horizontal(|ui| {
-                    let mut volume_percent = node.volume * 100.0;
-                    let muted = node.muted;
-                    let is_default = node.is_default;
+                    ui.add(
+                        egui::Label::new(egui::RichText::new(&node.name).small().weak()).truncate(),
+                    );
 
-                    if ui.selectable_label(muted, "Mute").clicked() {
-                        let _ = self.tx.send(PwCommand::SetMute(node.id, !muted));
+                    if inactive {
+                        ui.label(egui::RichText::new("inactive").small().weak())
+                            .on_hover_text("This device's card profile is off or unavailable");
                     }
 
-                    if !node.is_stream {
-                        if ui.selectable_label(is_default, "Default").clicked() {
-                            let _ = self.tx.send(PwCommand::SetDefault(node.id));
+                    render_link_badge(ui, node, state);
+
+                    if !node.is_stream && state.is_port_unplugged(node) {
+                        ui.label(egui::RichText::new("unplugged").small().weak())
+                            .on_hover_text("Nothing is plugged into this device's current port");
+                    }
+
+                    if let Some(note) = configured_default_note(node, state) {
+                        ui.label(egui::RichText::new(note.0).small().weak()).on_hover_text(note.1);
+                    }
+
+                    if self.settings.developer_mode {
+                        render_error_stats(ui, node);
+                    }
+
+                    if self.settings.show_volume_meters && !node.meter_available {
+                        ui.label(egui::RichText::new("no meter").small().weak())
+                            .on_hover_text("A level meter could not be attached to this node, so it shows no activity");
+                    }
+                });
+
+                if let Some(format) = &node.format {
+                    ui.label(egui::RichText::new(format.summary()).small().weak());
+                }
+
+                ui.add_enabled_ui(!self.settings.locked && !inactive, |ui| {
+                    ui.horizontal(|ui| self.render_volume_controls(ui, node, "Vol"))
+                });
+
+                if node.is_stream {
+                    ui.add_enabled_ui(!self.settings.locked, |ui| self.render_stream_target(ui, node, state));
+                }
+
+                if self.settings.show_volume_meters && node.meter_available {
+                    meter_scrolled = self.render_meter(ui, node);
+                }
+
+                // Stereo nodes get a single balance control; anything wider needs a slider per channel.
+                ui.add_enabled_ui(!self.settings.locked && !inactive, |ui| match node.channel_volumes.len() {
+                    0 | 1 => {}
+                    2 => self.render_balance(ui, node),
+                    _ => self.render_channels(ui, node),
+                });
+
+                self.render_volume_entry(ui, node);
+            });
+        });
+
+        if flash.is_some() && self.scrolled_to_default != flash {
+            response.response.scroll_to_me(Some(egui::Align::Center));
+            self.scrolled_to_default = flash;
+        }
+
+        if !self.settings.locked && !inactive && !meter_scrolled && response.response.contains_pointer() {
+            self.scroll_volume(ui, node);
+        }
+
+        self.node_order.push(node.id);
+        if self.focus_moved && self.selected_node == Some(node.id) {
+            response.response.scroll_to_me(None);
+            self.focus_moved = false;
+        }
+
+        let mut row = response.response.interact(egui::Sense::click());
+        if let Some(latency) = &node.latency {
+            row = row.on_hover_text(format!("Requested latency: {}", latency.summary()));
+        }
+        if row.clicked() {
+            self.selected_node = Some(node.id);
+        }
+
+        row.context_menu(|ui| self.render_node_menu(ui, node, state, inactive));
+    }
+
+    /// Mute and default toggles followed by the volume slider, labelled with `text`.
+    fn render_volume_controls(&mut self, ui: &mut egui::Ui, node: &AudioNode, text: &str) {
+        let mut volume_percent = node.volume * 100.0;
+        let muted = node.muted;
+        let is_default = node.is_default;
+
+        if ui.selectable_label(muted, "Mute").clicked() {
+            self.send_change(PwCommand::SetMute(node.id, !muted));
+        }
+
+        if !node.is_stream && ui.selectable_label(is_default, "Default").clicked() {
+            self.send_change(PwCommand::SetDefault(node.id));
+        }
+
+        let slider = self.volume_slider(ui, &mut volume_percent, text)
+            .on_hover_text("Double-click to reset to 100%");
+        if slider.double_clicked() {
+            self.send_change(PwCommand::SetVolume(node.id, 1.0));
+        } else if slider.changed() {
+            self.send_change(PwCommand::SetVolume(node.id, volume_percent / 100.0));
+        }
+    }
+
+    /// "Default output: <name>" above a device list; clicking it selects and scrolls to that node.
+    fn render_default_summary(&mut self, ui: &mut egui::Ui, default: Option<&AudioNode>, input: bool) {
+        ui.horizontal(|ui| {
+            ui.label(if input { "Default input:" } else { "Default output:" });
+            let Some(node) = default else {
+                ui.label(egui::RichText::new("none").weak());
+                return;
+            };
+
+            // A default input can be an output's monitor, which isn't listed among the inputs.
+            let name = if input && node.is_sink {
+                format!("Monitor of {}", node.description)
+            } else {
+                node.description.clone()
+            };
+            let link = ui.link(egui::RichText::new(name).color(self.accent)).on_hover_text("Show in the list");
+            if link.clicked() {
+                self.selected_node = Some(node.id);
+                self.focus_moved = true;
+            }
+        });
+    }
+
+    /// Buttons that mute or unmute every node in a list, skipping those already there.
+    fn render_mute_all(&mut self, ui: &mut egui::Ui, nodes: &[&AudioNode]) {
+        ui.add_enabled_ui(!self.settings.locked, |ui| {
+            ui.horizontal(|ui| {
+                let mut mute = None;
+                if ui.add_enabled(nodes.iter().any(|n| !n.muted), egui::Button::new("Mute all")).clicked() {
+                    mute = Some(true);
+                }
+                if ui.add_enabled(nodes.iter().any(|n| n.muted), egui::Button::new("Unmute all")).clicked() {
+                    mute = Some(false);
+                }
+                if let Some(mute) = mute {
+                    for node in nodes.iter().filter(|n| n.muted != mute) {
+                        self.send_change(PwCommand::SetMute(node.id, mute));
+                    }
+                }
+            });
+        });
+        ui.add_space(4.0);
+    }
+
+    fn render_node_menu(&mut self, ui: &mut egui::Ui, node: &AudioNode, state: &AppState, inactive: bool) {
+        ui.add_enabled_ui(!self.settings.locked && !inactive, |ui| {
+            if !node.is_stream && ui.add_enabled(!node.is_default, egui::Button::new("Set as default")).clicked() {
+                self.send_change(PwCommand::SetDefault(node.id));
+                ui.close();
+            }
+            if ui.button(if node.muted { "Unmute" } else { "Mute" }).clicked() {
+                self.send_change(PwCommand::SetMute(node.id, !node.muted));
+                ui.close();
+            }
+            if ui.button("Set volume to 100%").clicked() {
+                self.send_change(PwCommand::SetVolume(node.id, 1.0));
+                ui.close();
+            }
+            if node.is_stream {
+                ui.menu_button("Move to…", |ui| {
+                    for target in stream_targets(node, state) {
+                        let current = node.target_id == Some(target.id);
+                        if ui.add_enabled(!current, egui::Button::new(stream_target_text(node, target))).clicked() {
+                            self.send_change(PwCommand::MoveStream(node.id, Some(target.id)));
+                            ui.close();
                         }
                     }
+                });
+            }
+        });
 
-                    let slider = egui::Slider::new(&mut volume_percent, 0.0..=100.0)
-                        .show_value(true)
-                        .text("Vol")
-                        .suffix("%")
-                        .fixed_decimals(0);
+        ui.separator();
+        if ui.button("Copy node name").clicked() {
+            ui.ctx().copy_text(node.name.clone());
+            ui.close();
+        }
+        if !node.is_stream {
+            let mut persist = state.persist_for(node);
+            if ui.checkbox(&mut persist, "Remember volume for this device").changed() {
+                self.persist_toggle = Some((node.stable_id.clone(), persist));
+                ui.close();
+            }
 
-                    if ui.add(slider).changed() {
-                        let _ = self.tx.send(PwCommand::SetVolume(node.id, volume_percent / 100.0));
+            ui.menu_button("Link volume with…", |ui| {
+                let linked: Vec<u32> = state.linked_nodes(node).iter().map(|n| n.id).collect();
+                let mut others: Vec<&AudioNode> = state
+                    .nodes
+                    .values()
+                    .filter(|n| !n.is_stream && n.is_sink == node.is_sink && n.id != node.id)
+                    .collect();
+                others.sort_by_key(|n| n.id);
+                if others.is_empty() {
+                    ui.label(egui::RichText::new("No other devices").weak());
+                }
+                for other in others {
+                    let mut on = linked.contains(&other.id);
+                    if ui.checkbox(&mut on, &other.description).changed() {
+                        self.link_toggle = Some((node.stable_id.clone(), other.stable_id.clone(), on));
+                        ui.close();
+                    }
+                }
+            });
+        }
+    }
+
+    /// Pick the device a stream plays on or records from, or let it follow the default.
+    /// Recording streams can also take a sink, recording its monitor.
+    fn render_stream_target(&mut self, ui: &mut egui::Ui, node: &AudioNode, state: &AppState) {
+        let target_text = |target: &AudioNode| stream_target_text(node, target);
+
+        let default = if node.is_sink { state.find_default_sink() } else { state.find_default_source() };
+        let default_text = match default {
+            Some(device) => format!("Default ({})", target_text(device)),
+            None => "Default".to_string(),
+        };
+        let selected_text = match node.target_id.and_then(|id| state.nodes.get(&id)) {
+            Some(target) => target_text(target),
+            None => default_text.clone(),
+        };
+
+        let mut target = node.target_id;
+        egui::ComboBox::from_id_salt(("stream_target", node.id))
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut target, None, default_text);
+                for device in stream_targets(node, state) {
+                    ui.selectable_value(&mut target, Some(device.id), target_text(device));
+                }
+            });
+
+        if target != node.target_id {
+            self.send_change(PwCommand::MoveStream(node.id, target));
+        }
+    }
+
+    /// A thin bar showing the node's level on the same scale as the volume slider.
+    /// Scrolling over a source's meter sets its gain, like a knob next to a level display;
+    /// returns whether it took the wheel this frame.
+    fn render_meter(&mut self, ui: &mut egui::Ui, node: &AudioNode) -> bool {
+        let dt = ui.input(|i| i.stable_dt);
+        let level = self.meter_levels.entry(node.id).or_default();
+        *level = self.volume_curve.to_slider(node.peak).min(1.0).max(*level - METER_DECAY * dt);
+        if *level > 0.0 {
+            // The meter callbacks wake us at this rate too; faster frames wouldn't show more.
+            ui.ctx().request_repaint_after(METER_FRAME);
+        }
+
+        let (rect, response) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), METER_HEIGHT), egui::Sense::hover());
+        let painter = ui.painter();
+        painter.rect_filled(rect, 1.0, ui.visuals().extreme_bg_color);
+        let mut filled = rect;
+        filled.set_width(rect.width() * *level);
+        let color = if node.muted { ui.visuals().weak_text_color() } else { self.accent };
+        painter.rect_filled(filled, 1.0, color);
+
+        let hold = self.volume_curve.to_slider(node.peak_hold).min(1.0).max(*level);
+        if hold > 0.0 {
+            let x = rect.left() + (rect.width() * hold).max(1.0);
+            painter.vline(x, rect.y_range(), egui::Stroke::new(2.0, ui.visuals().strong_text_color()));
+        }
+
+        let scrolled = !node.is_sink && !self.settings.locked && response.contains_pointer();
+        if scrolled {
+            self.scroll_volume(ui, node);
+        }
+
+        response.on_hover_text(format!(
+            "Peak {} dB, hold {} dB",
+            format_db(node.peak),
+            format_db(node.peak_hold)
+        ));
+        scrolled
+    }
+
+    fn render_balance(&mut self, ui: &mut egui::Ui, node: &AudioNode) {
+        let mut balance = node.balance;
+        let slider = egui::Slider::new(&mut balance, -1.0..=1.0)
+            .text("Balance")
+            .custom_formatter(|b, _| match b {
+                b if b < -0.005 => format!("L {:.0}%", -b * 100.0),
+                b if b > 0.005 => format!("R {:.0}%", b * 100.0),
+                _ => "Center".to_string(),
+            });
+
+        if ui.add(slider).changed() {
+            self.send_change(PwCommand::SetChannelVolumes(node.id, stereo_levels(node.volume, balance)));
+        }
+    }
+
+    /// Turn mouse wheel movement over a node into volume steps, keeping it from scrolling the list.
+    fn scroll_volume(&mut self, ui: &mut egui::Ui, node: &AudioNode) {
+        let notches: f32 = ui.input_mut(|i| {
+            i.smooth_scroll_delta = egui::Vec2::ZERO;
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::MouseWheel { unit, delta, modifiers } if !modifiers.command => Some(match unit {
+                        egui::MouseWheelUnit::Point => delta.y / SCROLL_POINTS_PER_NOTCH,
+                        egui::MouseWheelUnit::Line | egui::MouseWheelUnit::Page => delta.y,
+                    }),
+                    _ => None,
+                })
+                .sum()
+        });
+
+        self.scroll_notches += notches;
+        let steps = self.scroll_notches.trunc();
+        if steps == 0.0 {
+            return;
+        }
+        self.scroll_notches -= steps;
+
+        let volume = (node.volume + steps * SCROLL_VOLUME_STEP).clamp(0.0, self.max_volume());
+        self.send_change(PwCommand::SetVolume(node.id, volume));
+    }
+
+    /// Each output as a collapsible group with the playback streams it is playing beneath
+    /// it, mirroring the graph. Streams whose output isn't known are listed at the end.
+    fn render_nested_outputs(&mut self, ui: &mut egui::Ui, sinks: &[&AudioNode], state: &AppState) {
+        let mut streams: HashMap<Option<u32>, Vec<&AudioNode>> = HashMap::new();
+        let default_sink = state.find_default_sink().map(|n| n.id);
+        for node in state.nodes.values().filter(|n| n.is_stream && n.is_sink) {
+            let sink = node
+                .target_id
+                .filter(|id| state.nodes.get(id).is_some_and(|t| t.is_sink && !t.is_stream))
+                .or(default_sink);
+            streams.entry(sink).or_default().push(node);
+        }
+        for group in streams.values_mut() {
+            group.sort_by_key(|n| n.id);
+        }
+
+        for sink in sinks {
+            let children = streams.remove(&Some(sink.id)).unwrap_or_default();
+            egui::CollapsingHeader::new(format!("{} {} ({})", node_icon(sink.kind), sink.description, children.len()))
+                .id_salt(("nested_sink", sink.id))
+                .default_open(true)
+                .show(ui, |ui| {
+                    self.render_node(ui, sink, state);
+                    ui.indent(("nested_streams", sink.id), |ui| {
+                        for node in &children {
+                            self.render_node(ui, node, state);
+                        }
+                    });
+                });
+        }
+
+        let unrouted: Vec<&AudioNode> = streams.into_values().flatten().collect();
+        if !unrouted.is_empty() {
+            egui::CollapsingHeader::new(format!("Other streams ({})", unrouted.len()))
+                .id_salt("nested_unrouted")
+                .default_open(true)
+                .show(ui, |ui| {
+                    for node in unrouted {
+                        self.render_node(ui, node, state);
                     }
                 });
+        }
+    }
+
+    /// One slider per channel, for adjusting balance between speakers.
+    fn render_channels(&mut self, ui: &mut egui::Ui, node: &AudioNode) {
+        egui::CollapsingHeader::new("Channels")
+            .id_salt(("channels", node.id))
+            .show(ui, |ui| {
+                let mut locked = self.channels_locked.contains(&node.id);
+                if ui
+                    .toggle_value(&mut locked, "🔒 Lock channels")
+                    .on_hover_text("Move all channels together, keeping their proportions")
+                    .changed()
+                {
+                    if locked {
+                        self.channels_locked.insert(node.id);
+                    } else {
+                        self.channels_locked.remove(&node.id);
+                    }
+                }
+
+                let mut levels = node.channel_volumes.clone();
+                let mut muted: Vec<bool> = (0..levels.len()).map(|i| node.channel_muted.get(i) == Some(&true)).collect();
+                let mut edited = None;
+                let mut mutes_changed = false;
+                for (i, level) in levels.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.checkbox(&mut muted[i], "").on_hover_text("Mute this channel").changed() {
+                            mutes_changed = true;
+                        }
+                        let solo = muted.iter().enumerate().all(|(j, &m)| m == (j != i));
+                        if ui.selectable_label(solo, "S").on_hover_text("Solo: mute the other channels").clicked() {
+                            muted = (0..muted.len()).map(|j| !solo && j != i).collect();
+                            mutes_changed = true;
+                        }
+
+                        let mut percent = *level * 100.0;
+                        let label = channel_label(node.channel_volumes.len(), i);
+                        if self.volume_slider(ui, &mut percent, label).changed() {
+                            *level = percent / 100.0;
+                            edited = Some(i);
+                        }
+                    });
+                }
+                if mutes_changed {
+                    self.send_change(PwCommand::SetChannelMutes(node.id, muted));
+                    return;
+                }
+                let Some(edited) = edited else { return };
+
+                if locked {
+                    let before = node.channel_volumes[edited];
+                    let after = levels[edited];
+                    let max_volume = self.max_volume();
+                    levels = node
+                        .channel_volumes
+                        .iter()
+                        // From silence there are no proportions left to keep.
+                        .map(|&v| (if before > 0.0 { v * after / before } else { after }).min(max_volume))
+                        .collect();
+                }
+                self.send_change(PwCommand::SetChannelVolumes(node.id, levels));
+            });
+    }
+
+    fn render_volume_entry(&mut self, ui: &mut egui::Ui, node: &AudioNode) {
+        let max_volume = self.max_volume();
+        let Some(entry) = self.volume_entry.as_mut().filter(|e| e.node_id == node.id) else { return };
+
+        let mut commit = false;
+        let mut cancel = false;
+        ui.horizontal(|ui| {
+            ui.label("Set volume:");
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut entry.text)
+                    .desired_width(80.0)
+                    .hint_text("% or dB"),
+            );
+            if entry.request_focus {
+                edit.request_focus();
+                entry.request_focus = false;
+            }
+            if edit.lost_focus() {
+                if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    commit = true;
+                } else {
+                    cancel = true;
+                }
+            }
+        });
+
+        if commit {
+            match parse_volume_entry(&entry.text, max_volume, self.volume_curve) {
+                Some(volume) => {
+                    self.send_change(PwCommand::SetVolume(node.id, volume));
+                    self.volume_entry = None;
+                }
+                None => entry.request_focus = true,
+            }
+        } else if cancel {
+            self.volume_entry = None;
+        }
+    }
+
+    /// One row for all streams of an application: a master volume that scales every
+    /// stream proportionally, and an expander with the individual streams.
+    fn render_app_group(&mut self, ui: &mut egui::Ui, members: &[&AudioNode], state: &AppState) {
+        let app_name = members[0].app_name.as_deref().unwrap_or("Unknown");
+        let master = members.iter().map(|n| n.volume).fold(0.0, f32::max);
+
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(app_name).strong());
+                ui.label(egui::RichText::new(format!("{} streams", members.len())).small().weak());
+            });
+
+            ui.add_enabled_ui(!self.settings.locked, |ui| {
+                let mut volume_percent = master * 100.0;
+                if ui.horizontal(|ui| self.volume_slider(ui, &mut volume_percent, "Vol")).inner.changed() {
+                    let new_master = volume_percent / 100.0;
+                    for node in members {
+                        // Streams keep their proportions; if everything was silent, move together.
+                        let volume = if master > 0.0 { node.volume * new_master / master } else { new_master };
+                        self.send_change(PwCommand::SetVolume(node.id, volume.clamp(0.0, self.max_volume())));
+                    }
+                }
+            });
+
+            egui::CollapsingHeader::new("Streams")
+                .id_salt(("app_group", app_name))
+                .show(ui, |ui| {
+                    for node in members {
+                        self.render_node(ui, node, state);
+                    }
+                });
+        });
+    }
+
+    /// Send a command that changes the audio setup, unless the UI is locked.
+    fn send_change(&self, command: PwCommand) {
+        if !self.settings.locked {
+            let _ = self.tx.send(command);
+        }
+    }
+
+    /// Recovery for a view that got out of step with PipeWire; not a change to the audio
+    /// setup, so it works while locked too.
+    fn rescan(&self) {
+        let _ = self.tx.send(PwCommand::Rescan);
+    }
+
+    fn quit(&mut self, ctx: &egui::Context) {
+        self.quitting = true;
+        // `on_exit` saves the session; `main` then stops the PipeWire thread.
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    /// Whether closing should hide the window; only when a tray icon can bring it back.
+    fn hides_to_tray(&self) -> bool {
+        self.settings.close_to_tray && self.tray.is_available()
+    }
+
+    /// Push the default output's state to the tray icon.
+    fn sync_tray(&mut self) {
+        let sink = self.state.lock().find_default_sink().map(|n| (n.volume, n.muted));
+        self.tray.set_status(TrayStatus {
+            volume: sink.map(|(volume, _)| volume),
+            muted: sink.is_some_and(|(_, muted)| muted),
+            max_volume: self.max_volume(),
+            locked: self.settings.locked,
+            scroll_step: self.settings.tray_scroll_step,
+        });
+    }
+
+    /// Save the window geometry and open tab for the next launch.
+    fn save_session(&self) {
+        config::save_session(&Session {
+            tab: Some(self.current_tab.name().to_string()),
+            accent_color: Some([self.accent.r(), self.accent.g(), self.accent.b()]),
+            theme: self.theme,
+            window: self.window,
+            always_on_top: self.always_on_top,
+            borderless: self.borderless,
+            settings: self.settings.clone(),
+        });
+    }
+
+    /// The top of the volume range in slider units, above 1.0 when boosting is allowed.
+    fn max_volume(&self) -> f32 {
+        if self.settings.allow_overamplification { MAX_BOOSTED_VOLUME } else { 1.0 }
+    }
+
+    /// A volume slider and a field for typing an exact value, which applies on Enter. Tinted
+    /// as a clipping warning while above 100%. The value is always in percent; in dB mode
+    /// both show and edit it in decibels instead, and with `log_volume_slider` only the slider
+    /// does. The returned response is the slider's, marked changed when the field is edited too.
+    fn volume_slider(&self, ui: &mut egui::Ui, percent: &mut f32, text: impl Into<egui::WidgetText>) -> egui::Response {
+        let boosted = *percent > 100.0;
+
+        ui.scope(|ui| {
+            if boosted {
+                ui.visuals_mut().selection.bg_fill = ui.visuals().error_fg_color;
+            }
+
+            let response = if self.settings.volume_in_db {
+                let mut db = volume_to_db(*percent / 100.0, self.volume_curve);
+                let range = MIN_DB..=volume_to_db(self.max_volume(), self.volume_curve);
+                let mut response = ui.add(egui::Slider::new(&mut db, range.clone()).show_value(false).trailing_fill(boosted));
+                let field = ui.add(
+                    egui::DragValue::new(&mut db)
+                        .range(range)
+                        .suffix(" dB")
+                        .fixed_decimals(1)
+                        .update_while_editing(false)
+                        .custom_formatter(|db, decimals| {
+                            if db <= MIN_DB as f64 {
+                                "-∞".to_string()
+                            } else {
+                                format!("{:.*}", decimals.start(), db)
+                            }
+                        }),
+                );
+                if field.changed() {
+                    response.mark_changed();
+                }
+                if response.changed() {
+                    *percent = db_to_volume(db, self.volume_curve) * 100.0;
+                }
+                response
+            } else {
+                let range = 0.0..=self.max_volume() * 100.0;
+                let mut response = if self.settings.log_volume_slider {
+                    // The slider travels in dB, the same loudness step per pixel; the value stays in percent.
+                    let mut db = volume_to_db(*percent / 100.0, self.volume_curve);
+                    let db_range = MIN_DB..=volume_to_db(self.max_volume(), self.volume_curve);
+                    let response = ui.add(egui::Slider::new(&mut db, db_range).show_value(false).trailing_fill(boosted));
+                    if response.changed() {
+                        *percent = db_to_volume(db, self.volume_curve) * 100.0;
+                    }
+                    response
+                } else {
+                    ui.add(egui::Slider::new(&mut *percent, range.clone()).show_value(false).trailing_fill(boosted))
+                };
+                let field = ui.add(
+                    egui::DragValue::new(percent)
+                        .range(range)
+                        .suffix("%")
+                        .fixed_decimals(0)
+                        .update_while_editing(false),
+                );
+                if field.changed() {
+                    response.mark_changed();
+                }
+                response
+            };
+
+            ui.label(text);
+            response
+        })
+        .inner
+    }
+
+    /// Adjust the default sink's volume, independent of tab and selection.
+    fn nudge_default_sink(&mut self, step: f32) {
+        let (sink_id, volume) = {
+            if self.settings.locked {
+                return;
+            }
+            let state = self.state.lock();
+            let Some(sink) = state.find_default_sink() else { return };
+            (sink.id, (sink.volume + step).clamp(0.0, self.max_volume()))
+        };
+
+        let _ = self.tx.send(PwCommand::SetVolume(sink_id, volume));
+        self.show_osd(sink_id);
+    }
+
+    fn show_osd(&mut self, node_id: u32) {
+        if self.settings.show_volume_osd {
+            self.osd = Some(Osd {
+                node_id,
+                shown_at: Instant::now(),
             });
+        }
+    }
+
+    /// Draw the borderless always-on-top OSD while a recent change is being shown.
+    fn render_osd(&mut self, ctx: &egui::Context) {
+        let Some(osd) = self.osd else { return };
+
+        let elapsed = osd.shown_at.elapsed();
+        if elapsed >= OSD_DURATION {
+            self.osd = None;
+            return;
+        }
+        let opacity = ((OSD_DURATION - elapsed).as_secs_f32() / OSD_FADE.as_secs_f32()).min(1.0);
+
+        let Some((description, volume, muted)) = self
+            .state
+            .lock()
+            .nodes
+            .get(&osd.node_id)
+            .map(|n| (n.description.clone(), n.volume, n.muted))
+        else {
+            self.osd = None;
+            return;
+        };
+
+        let draw = |ui: &mut egui::Ui| {
+            ui.multiply_opacity(opacity);
+            ui.add(egui::Label::new(egui::RichText::new(&description).strong()).truncate());
+            let text = if muted {
+                "Muted".to_string()
+            } else {
+                format!("{:.0}%", volume * 100.0)
+            };
+            ui.add(egui::ProgressBar::new(volume).text(text).fill(self.accent));
+        };
+
+        let builder = egui::ViewportBuilder::default()
+            .with_title("Copper OSD")
+            .with_inner_size([260.0, 64.0])
+            .with_decorations(false)
+            .with_resizable(false)
+            .with_taskbar(false)
+            .with_active(false)
+            .with_always_on_top();
+
+        ctx.show_viewport_immediate(egui::ViewportId::from_hash_of("copper_osd"), builder, |ctx, class| {
+            if class == egui::ViewportClass::Embedded {
+                egui::Area::new(egui::Id::new("copper_osd"))
+                    .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+                    .show(ctx, |ui| egui::Frame::popup(ui.style()).show(ui, draw));
+            } else {
+                egui::CentralPanel::default().show(ctx, draw);
+            }
+        });
+
+        ctx.request_repaint_after(Duration::from_millis(30));
+    }
+
+    /// The tabs to show in the tab bar; with `hide_empty_tabs`, node tabs without nodes
+    /// are left out. Configuration is always present, so the list is never empty.
+    fn visible_tabs(&self) -> Vec<(Tab, &'static str)> {
+        let tabs: [(Tab, &str, Option<NodeFilter>); 7] = [
+            (Tab::Outputs, "Outputs", Some(|n| n.is_sink && !n.is_stream)),
+            (Tab::Inputs, "Inputs", Some(|n| !n.is_sink && !n.is_stream)),
+            (Tab::Playback, "Playback", Some(|n| n.is_stream && n.is_sink)),
+            (Tab::Recording, "Recording", Some(|n| n.is_stream && !n.is_sink)),
+            (Tab::Configuration, "Configuration", None),
+            (Tab::All, "All", Some(|_| true)),
+            (Tab::Links, "Links", None),
+        ];
+
+        let state = self.state.lock();
+        tabs.into_iter()
+            .filter(|(_, _, filter)| {
+                !self.settings.hide_empty_tabs || filter.is_none_or(|f| state.nodes.values().any(f))
+            })
+            .map(|(tab, label, _)| (tab, label))
+            .collect()
+    }
+
+    /// Number keys 1–7 open the tab at that position in `Tab::ALL`; Ctrl+Tab and
+    /// Ctrl+Shift+Tab cycle through the visible tabs.
+    fn handle_tab_keys(&mut self, ctx: &egui::Context) {
+        use egui::{Key, Modifiers};
+
+        const DIGITS: [Key; 7] = [Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7];
+
+        let (digit, cycle) = ctx.input_mut(|i| {
+            let digit = DIGITS.iter().position(|&key| i.consume_key(Modifiers::NONE, key));
+            // Ctrl+Shift+Tab first, and before focus keys see Tab at all.
+            let cycle = if i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Tab) {
+                -1
+            } else if i.consume_key(Modifiers::COMMAND, Key::Tab) {
+                1
+            } else {
+                0
+            };
+            (digit, cycle)
+        });
+
+        let visible: Vec<Tab> = self.visible_tabs().into_iter().map(|(tab, _)| tab).collect();
+        if let Some(tab) = digit.map(|i| Tab::ALL[i]) {
+            if visible.contains(&tab) {
+                self.current_tab = tab;
+            }
+        } else if cycle != 0 {
+            let current = visible.iter().position(|&t| t == self.current_tab).unwrap_or(0) as isize;
+            let next = (current + cycle).rem_euclid(visible.len() as isize);
+            self.current_tab = visible[next as usize];
+        }
+    }
+
+    /// Tab/Shift+Tab move focus between nodes; arrows and Page Up/Down change the focused
+    /// node's volume and M toggles its mute.
+    fn handle_focus_keys(&mut self, ctx: &egui::Context) {
+        use egui::{Key, Modifiers};
+
+        let (backward, forward, step, mute) = ctx.input_mut(|i| {
+            // Shift+Tab first: the unmodified pattern would match it too.
+            let backward = i.consume_key(Modifiers::SHIFT, Key::Tab);
+            let forward = !backward && i.consume_key(Modifiers::NONE, Key::Tab);
+            let step = [
+                (Key::ArrowUp, FOCUS_VOLUME_STEP),
+                (Key::ArrowDown, -FOCUS_VOLUME_STEP),
+                (Key::PageUp, FOCUS_VOLUME_PAGE),
+                (Key::PageDown, -FOCUS_VOLUME_PAGE),
+            ]
+            .into_iter()
+            .find(|(key, _)| i.consume_key(Modifiers::NONE, *key))
+            .map(|(_, step)| step);
+            let mute = i.consume_key(Modifiers::NONE, Key::M);
+            (backward, forward, step, mute)
+        });
+
+        if backward || forward || step.is_some() || mute {
+            // These keys are ours; don't let egui also move its widget focus with them.
+            ctx.memory_mut(|m| m.move_focus(egui::FocusDirection::None));
+        }
+
+        if (backward || forward) && !self.node_order.is_empty() {
+            let len = self.node_order.len();
+            let current = self.selected_node.and_then(|id| self.node_order.iter().position(|n| *n == id));
+            let next = match (current, forward) {
+                (None, true) => 0,
+                (None, false) => len - 1,
+                (Some(i), true) => (i + 1) % len,
+                (Some(i), false) => (i + len - 1) % len,
+            };
+            self.selected_node = Some(self.node_order[next]);
+            self.focus_moved = true;
+        }
+
+        let Some(node_id) = self.selected_node else { return };
+        let Some((volume, muted)) = self.state.lock().nodes.get(&node_id).map(|n| (n.volume, n.muted)) else { return };
+        if let Some(step) = step {
+            self.send_change(PwCommand::SetVolume(node_id, (volume + step).clamp(0.0, self.max_volume())));
+        }
+        if mute {
+            self.send_change(PwCommand::SetMute(node_id, !muted));
+        }
+    }
+
+    fn open_volume_entry(&mut self) {
+        let Some(node_id) = self.selected_node else { return };
+        if self.settings.locked {
+            return;
+        }
+        let Some(volume) = self.state.lock().nodes.get(&node_id).map(|n| n.volume) else { return };
+
+        self.volume_entry = Some(VolumeEntry {
+            node_id,
+            text: format!("{:.0}", volume * 100.0),
+            request_focus: true,
         });
     }
 }
 
+/// Gain in dB of a volume in slider units.
+fn volume_to_db(volume: f32, curve: VolumeCurve) -> f32 {
+    let gain = curve.to_gain(volume);
+    if gain <= 0.0 {
+        return MIN_DB;
+    }
+    (20.0 * gain.log10()).max(MIN_DB)
+}
+
+/// A linear level in dB for display, "-∞" at silence.
+fn format_db(level: f32) -> String {
+    let db = volume_to_db(level, VolumeCurve::Linear);
+    if db <= MIN_DB {
+        "-∞".to_string()
+    } else {
+        format!("{:.1}", db)
+    }
+}
+
+/// Slider units for a gain in dB, with the bottom of the scale meaning silence.
+fn db_to_volume(db: f32, curve: VolumeCurve) -> f32 {
+    if db <= MIN_DB {
+        return 0.0;
+    }
+    curve.to_slider(10f32.powf(db / 20.0))
+}
+
+/// Parse a typed volume into slider units.
+///
+/// Accepts a percentage ("75", "75%") or a gain in decibels ("-6dB").
+fn parse_volume_entry(text: &str, max_volume: f32, curve: VolumeCurve) -> Option<f32> {
+    let text = text.trim().to_ascii_lowercase();
+
+    let volume = if let Some(db) = text.strip_suffix("db") {
+        let db: f32 = db.trim().parse().ok()?;
+        curve.to_slider(10f32.powf(db / 20.0))
+    } else {
+        let percent: f32 = text.trim_end_matches('%').trim().parse().ok()?;
+        percent / 100.0
+    };
+
+    volume.is_finite().then_some(volume.clamp(0.0, max_volume))
+}
+
+/// Switch egui's visuals to the theme; `System` follows the desktop's dark-mode hint.
+pub fn apply_theme(ctx: &egui::Context, theme: Theme) {
+    ctx.set_theme(match theme {
+        Theme::Dark => egui::ThemePreference::Dark,
+        Theme::Light => egui::ThemePreference::Light,
+        Theme::System => egui::ThemePreference::System,
+    });
+}
+
+/// The window's inner size and outer position, as far as the platform reports them.
+fn window_geometry(ctx: &egui::Context) -> Option<WindowGeometry> {
+    ctx.input(|i| {
+        let viewport = i.viewport();
+        let size = viewport.inner_rect?.size();
+        let position = viewport.outer_rect.map(|r| r.min);
+        Some(WindowGeometry {
+            width: size.x,
+            height: size.y,
+            x: position.map(|p| p.x),
+            y: position.map(|p| p.y),
+        })
+    })
+}
+
 impl eframe::App for CopperApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_session();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if ctx.input(|i| i.key_pressed(egui::Key::Escape) || i.key_pressed(egui::Key::Q)) {
-            let _ = self.tx.send(PwCommand::Quit);
-            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        // Keys typed into a text field (or answering a dialog) belong there, not to the shortcuts.
+        let typing = ctx.wants_keyboard_input()
+            || self.volume_entry.is_some()
+            || self.confirm.is_some()
+            || self.switcher.is_some();
+
+        if let Some(window) = window_geometry(ctx) {
+            self.window = Some(window);
+        }
+
+        self.sync_tray();
+        if self.tray.quit_requested() && !self.quitting {
+            self.quit(ctx);
+        }
+
+        if ctx.input(|i| i.viewport().close_requested()) && !self.quitting && self.hides_to_tray() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        }
+
+        if !typing && ctx.input(|i| i.key_pressed(egui::Key::Escape) || i.key_pressed(egui::Key::Q)) {
+            if self.hides_to_tray() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+            } else {
+                self.quit(ctx);
+            }
+        }
+
+        if !typing && ctx.input(|i| i.key_pressed(egui::Key::F5)) {
+            self.rescan();
+        }
+
+        if !typing && ctx.input(|i| i.key_pressed(egui::Key::V)) {
+            self.open_volume_entry();
+        }
+
+        if !typing && !self.settings.locked && ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::K)) {
+            self.switcher = Some(Switcher::default());
+        }
+
+        if !typing {
+            self.handle_tab_keys(ctx);
+            self.handle_focus_keys(ctx);
         }
 
+        if !typing {
+            let step = ctx.input(|i| {
+                if !i.modifiers.command {
+                    0.0
+                } else if i.key_pressed(egui::Key::ArrowUp) {
+                    KEYBOARD_VOLUME_STEP
+                } else if i.key_pressed(egui::Key::ArrowDown) {
+                    -KEYBOARD_VOLUME_STEP
+                } else {
+                    0.0
+                }
+            });
+            if step != 0.0 {
+                self.nudge_default_sink(step);
+            }
+        }
+
+        self.apply_pending_preset();
+        self.render_osd(ctx);
+        self.render_confirm(ctx);
+        self.render_switcher(ctx);
+        self.render_toasts(ctx);
+
+        self.node_order.clear();
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Copper");
+            ui.horizontal(|ui| {
+                ui.heading("Copper");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if self.settings.locked {
+                        if ui.button("Unlock").clicked() {
+                            self.settings.locked = false;
+                        }
+                        ui.label(egui::RichText::new("🔒 Locked").color(self.accent))
+                            .on_hover_text("Controls are read-only until unlocked");
+                    } else if ui.button("🔒 Lock").on_hover_text("Make all controls read-only").clicked() {
+                        self.settings.locked = true;
+                        self.volume_entry = None;
+                    }
+                });
+            });
             ui.add_space(10.0);
 
+            if !self.state.lock().connected {
+                ui.label(egui::RichText::new("Reconnecting to PipeWire…").color(ui.visuals().warn_fg_color))
+                    .on_hover_text("The PipeWire daemon can't be reached; devices reappear once it is back");
+                ui.add_space(10.0);
+            }
+
+            let visible_tabs = self.visible_tabs();
+            if !visible_tabs.iter().any(|(tab, _)| *tab == self.current_tab) {
+                self.current_tab = visible_tabs[0].0;
+            }
+
             ui.horizontal(|ui| {
-                ui.selectable_value(&mut self.current_tab, Tab::Outputs, "Outputs");
-                ui.selectable_value(&mut self.current_tab, Tab::Inputs, "Inputs");
-                ui.selectable_value(&mut self.current_tab, Tab::Playback, "Playback");
-                ui.selectable_value(&mut self.current_tab, Tab::Recording, "Recording");
-                ui.selectable_value(&mut self.current_tab, Tab::Configuration, "Configuration");
+                for (tab, label) in visible_tabs {
+                    let shortcut = Tab::ALL.iter().position(|t| *t == tab).map_or(0, |i| i + 1);
+                    ui.selectable_value(&mut self.current_tab, tab, label)
+                        .on_hover_text(format!("Press {} to open, Ctrl+Tab / Ctrl+Shift+Tab to cycle tabs", shortcut));
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("⟳").on_hover_text("Reload all devices and streams from PipeWire (F5)").clicked() {
+                        self.rescan();
+                    }
+                });
             });
 
             ui.add_space(10.0);
 
-            let mut state = self.state.lock();
+            let state_handle = self.state.clone();
+            let mut state = state_handle.lock();
+            self.settings.apply_to(&mut state);
             egui::ScrollArea::vertical()
                 .auto_shrink([false, false])
                 .show(ui, |ui| {
@@ -143,8 +1387,14 @@ impl eframe::App for CopperApp {
                             if sinks.is_empty() {
                                 ui.label("No output devices found");
                             } else {
-                                for node in sinks {
-                                    self.render_node(ui, node, &state);
+                                self.render_default_summary(ui, state.find_default_sink(), false);
+                                self.render_mute_all(ui, &sinks);
+                                if self.settings.nest_streams {
+                                    self.render_nested_outputs(ui, &sinks, &state);
+                                } else {
+                                    for node in sinks {
+                                        self.render_node(ui, node, &state);
+                                    }
                                 }
                             }
                         }
@@ -155,13 +1405,35 @@ impl eframe::App for CopperApp {
                                 .filter(|n| !n.is_sink && !n.is_stream)
                                 .collect();
                             sources.sort_by_key(|n| n.id);
+                            let (monitors, mut sources): (Vec<&AudioNode>, Vec<&AudioNode>) =
+                                sources.into_iter().partition(|n| n.is_monitor);
+                            let show_monitors = !self.settings.hide_monitors && !monitors.is_empty();
 
-                            if sources.is_empty() {
+                            if sources.is_empty() && !show_monitors {
                                 ui.label("No input devices found");
                             } else {
-                                for node in sources {
+                                if show_monitors {
+                                    sources.extend(&monitors);
+                                }
+                                self.render_default_summary(ui, state.find_default_source(), true);
+                                self.render_mute_all(ui, &sources);
+                                for node in sources.iter().filter(|n| !n.is_monitor) {
                                     self.render_node(ui, node, &state);
                                 }
+
+                                if show_monitors {
+                                    ui.add_space(6.0);
+                                    ui.separator();
+                                    ui.label(
+                                        egui::RichText::new(format!("Monitors ({})", monitors.len()))
+                                            .strong()
+                                            .color(self.accent),
+                                    )
+                                    .on_hover_text("Loopbacks of outputs, for recording what is playing");
+                                    for node in monitors {
+                                        self.render_node(ui, node, &state);
+                                    }
+                                }
                             }
                         }
                         Tab::Playback => {
@@ -172,11 +1444,24 @@ impl eframe::App for CopperApp {
                                 .collect();
                             playback.sort_by_key(|n| n.id);
 
+                            self.render_role_filter(ui, &playback);
+                            playback.retain(|n| self.role_filter.matches(n));
+
                             if playback.is_empty() {
                                 ui.label("No playback streams found");
                             } else {
-                                for node in playback {
-                                    self.render_node(ui, node, &state);
+                                self.render_mute_all(ui, &playback);
+                                if self.settings.group_by_app {
+                                    for group in group_by_app(playback) {
+                                        match group.as_slice() {
+                                            [node] => self.render_node(ui, node, &state),
+                                            members => self.render_app_group(ui, members, &state),
+                                        }
+                                    }
+                                } else {
+                                    for node in playback {
+                                        self.render_node(ui, node, &state);
+                                    }
                                 }
                             }
                         }
@@ -191,40 +1476,464 @@ impl eframe::App for CopperApp {
                             if recording.is_empty() {
                                 ui.label("No recording streams found");
                             } else {
+                                self.render_mute_all(ui, &recording);
                                 for node in recording {
                                     self.render_node(ui, node, &state);
                                 }
                             }
                         }
                         Tab::Configuration => {
-                            let mut cards: Vec<&crate::state::Card> = state.cards.values().collect();
+                            let mut cards: Vec<&Card> = state.cards.values().collect();
                             cards.sort_by_key(|c| c.id);
 
                             if cards.is_empty() {
                                 ui.label("No audio cards found");
                             } else {
                                 for card in cards {
-                                    if state.hide_unavailable_profiles && card.profiles.iter().all(|p| !p.available) {
+                                    if self.settings.hide_unavailable_profiles && card.profiles.iter().all(|p| !p.available) {
                                         continue;
                                     }
                                     self.render_card(ui, card, &state);
                                 }
                             }
+
+                            ui.add_space(10.0);
+                            self.render_presets(ui, &state);
+                            self.render_clock(ui, &state);
+                            self.render_settings(ui);
+                        }
+                        Tab::All => {
+                            let sections: [(&str, NodeFilter); 4] = [
+                                ("Outputs", |n| n.is_sink && !n.is_stream),
+                                ("Inputs", |n| !n.is_sink && !n.is_stream),
+                                ("Playback", |n| n.is_stream && n.is_sink),
+                                ("Recording", |n| n.is_stream && !n.is_sink),
+                            ];
+
+                            let mut shown_any = false;
+                            for (title, filter) in sections {
+                                let mut nodes: Vec<&AudioNode> = state.nodes.values().filter(|n| filter(n)).collect();
+                                if nodes.is_empty() {
+                                    continue;
+                                }
+                                nodes.sort_by_key(|n| n.id);
+
+                                if shown_any {
+                                    ui.add_space(6.0);
+                                    ui.separator();
+                                }
+                                shown_any = true;
+
+                                ui.label(
+                                    egui::RichText::new(format!("{} ({})", title, nodes.len()))
+                                        .strong()
+                                        .color(self.accent),
+                                );
+                                for node in nodes {
+                                    self.render_node(ui, node, &state);
+                                }
+                            }
+
+                            if !shown_any {
+                                ui.label("No audio devices or streams found");
+                            }
+                        }
+                        Tab::Links => {
+                            self.render_connect(ui, &state);
+                            ui.add_space(6.0);
+                            self.render_links(ui, &state);
                         }
                     }
                 });
 
+            if let Some((stable_id, persist)) = self.persist_toggle.take() {
+                self.settings.set_persist_override(&stable_id, persist);
+                self.settings.apply_to(&mut state);
+            }
+            if let Some((stable_id, other, linked)) = self.link_toggle.take() {
+                state.set_volume_link(&stable_id, &other, linked);
+                config::save_volume_groups(&state.volume_groups);
+            }
+
             ui.separator();
             ui.horizontal(|ui| {
-                ui.checkbox(&mut state.show_volume_meters, "Show volume meters");
-                ui.checkbox(&mut state.hide_unavailable_profiles, "Hide unavailable card profiles");
+                ui.checkbox(&mut self.settings.show_volume_meters, "Show volume meters");
+                ui.checkbox(&mut self.settings.hide_unavailable_profiles, "Hide unavailable card profiles and ports");
+                ui.checkbox(&mut self.settings.allow_overamplification, "Allow volume above 100%")
+                    .on_hover_text("Boosting past 100% can clip and distort");
+                ui.checkbox(&mut self.settings.volume_in_db, "Volume in dB");
+                ui.add_enabled(
+                    !self.settings.volume_in_db,
+                    egui::Checkbox::new(&mut self.settings.log_volume_slider, "Logarithmic slider"),
+                )
+                .on_hover_text("Every step of the slider is the same change in dB, for finer control at low volumes");
+                ui.checkbox(&mut self.settings.nest_streams, "Nest streams under outputs")
+                    .on_hover_text("Show each output with the playback streams routed to it");
+                ui.checkbox(&mut self.settings.group_by_app, "Group streams by application");
+                ui.checkbox(&mut self.settings.hide_monitors, "Hide monitor inputs");
+                ui.checkbox(&mut self.settings.compact, "Compact")
+                    .on_hover_text("One row per device or stream, with only mute, default and volume");
             });
         });
     }
 }
 
 impl CopperApp {
-    fn render_card(&self, ui: &mut egui::Ui, card: &crate::state::Card, state: &AppState) {
+    fn render_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("Settings").strong().color(self.accent));
+
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.checkbox(&mut self.settings.auto_unmute_on_call, "Unmute default input when a call starts")
+                .on_hover_text("Triggered by recording streams with the communication role or from the apps below");
+
+            ui.add_enabled_ui(self.settings.auto_unmute_on_call, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Call apps:");
+                    let edit = ui.add(
+                        egui::TextEdit::singleline(&mut self.call_apps_text)
+                            .hint_text("comma-separated")
+                            .desired_width(f32::INFINITY),
+                    );
+                    if edit.changed() {
+                        self.settings.set_call_apps(&self.call_apps_text);
+                    }
+                });
+            });
+
+            ui.checkbox(&mut self.settings.volume_feedback_sound, "Play a sound when changing output volume");
+            ui.checkbox(&mut self.settings.volume_notifications, "Show a notification when changing output volume");
+            ui.checkbox(&mut self.settings.persist_changes, "Remember device volumes across restarts")
+                .on_hover_text("Individual devices can override this from their right-click menu");
+            ui.checkbox(&mut self.settings.show_volume_osd, "Show on-screen display for hotkey volume changes");
+            ui.checkbox(&mut self.settings.hide_empty_tabs, "Hide tabs with nothing to show");
+            ui.checkbox(&mut self.settings.close_to_tray, "Close to tray instead of quitting")
+                .on_hover_text("Only while a system tray is showing Copper's icon");
+            ui.checkbox(&mut self.settings.confirm_profile_changes, "Confirm profile changes that remove the default device");
+            ui.checkbox(&mut self.settings.developer_mode, "Developer mode")
+                .on_hover_text("Show per-node diagnostics such as error counts");
+
+            ui.horizontal(|ui| {
+                ui.label("Tray scroll step:");
+                let mut percent = self.settings.tray_scroll_step * 100.0;
+                let field = egui::DragValue::new(&mut percent).range(1.0..=20.0).suffix("%").fixed_decimals(0);
+                if ui.add(field).on_hover_text("Volume change per scroll notch over the tray icon").changed() {
+                    self.settings.tray_scroll_step = percent / 100.0;
+                }
+            });
+
+            if ui.checkbox(&mut self.always_on_top, "Always on top").changed() {
+                let level = if self.always_on_top { egui::WindowLevel::AlwaysOnTop } else { egui::WindowLevel::Normal };
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+            }
+            if ui
+                .checkbox(&mut self.borderless, "Borderless window")
+                .on_hover_text("Without a title bar, move the window with your window manager's shortcuts")
+                .changed()
+            {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Decorations(!self.borderless));
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                let before = self.theme;
+                ui.selectable_value(&mut self.theme, Theme::System, "System");
+                ui.selectable_value(&mut self.theme, Theme::Light, "Light");
+                ui.selectable_value(&mut self.theme, Theme::Dark, "Dark");
+                if self.theme != before {
+                    apply_theme(ui.ctx(), self.theme);
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Accent color:");
+                let mut rgb = [self.accent.r(), self.accent.g(), self.accent.b()];
+                if ui.color_edit_button_srgb(&mut rgb).changed() {
+                    self.accent = egui::Color32::from_rgb(rgb[0], rgb[1], rgb[2]);
+                }
+                if self.accent != DEFAULT_ACCENT && ui.small_button("Reset").clicked() {
+                    self.accent = DEFAULT_ACCENT;
+                }
+            });
+        });
+    }
+
+    /// Switch a card's profile, asking first if that would remove the default device.
+    fn request_profile(&mut self, card: &Card, profile_index: u32, state: &AppState) {
+        if self.settings.confirm_profile_changes
+            && let Some(node) = state.default_lost_by_profile(card.id, profile_index)
+        {
+            let profile = card
+                .profiles
+                .iter()
+                .find(|p| p.index == profile_index)
+                .map_or("this profile", |p| p.description.as_str());
+            let kind = if node.is_sink { "output" } else { "input" };
+            self.confirm = Some(Confirm {
+                title: "Change profile?",
+                message: format!(
+                    "Switching {} to {} will remove your default {}, {}.",
+                    card.description, profile, kind, node.description
+                ),
+                action: "Switch anyway",
+                command: PwCommand::SetCardProfile(card.id, profile_index),
+            });
+            return;
+        }
+
+        self.send_change(PwCommand::SetCardProfile(card.id, profile_index));
+    }
+
+    /// Failures reported by the PipeWire thread, stacked in the bottom right until dismissed.
+    fn render_toasts(&self, ctx: &egui::Context) {
+        let mut state = self.state.lock();
+        if state.messages.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(320.0);
+                for (i, message) in state.messages.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                                dismissed = Some(i);
+                            }
+                            let text = egui::RichText::new(message).color(ui.visuals().error_fg_color);
+                            ui.add(egui::Label::new(text).wrap());
+                        });
+                    });
+                }
+            });
+
+        if let Some(i) = dismissed {
+            state.messages.remove(i);
+        }
+    }
+
+    /// Ctrl+K popup: type to filter outputs, Enter makes the highlighted one the default.
+    fn render_switcher(&mut self, ctx: &egui::Context) {
+        let Some(switcher) = self.switcher.as_mut() else { return };
+
+        let query = switcher.query.to_lowercase();
+        let mut sinks: Vec<(u32, String, bool)> = self
+            .state
+            .lock()
+            .nodes
+            .values()
+            .filter(|n| n.is_sink && !n.is_stream)
+            .filter(|n| n.description.to_lowercase().contains(&query) || n.name.to_lowercase().contains(&query))
+            .map(|n| (n.id, n.description.clone(), n.is_default))
+            .collect();
+        sinks.sort_by_key(|(id, _, _)| *id);
+
+        let (up, down, enter) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::Enter),
+            )
+        });
+        if down {
+            switcher.highlighted += 1;
+        }
+        if up {
+            switcher.highlighted = switcher.highlighted.saturating_sub(1);
+        }
+        switcher.highlighted = switcher.highlighted.min(sinks.len().saturating_sub(1));
+
+        let mut chosen = enter.then(|| sinks.get(switcher.highlighted).map(|(id, _, _)| *id)).flatten();
+        let modal = egui::Modal::new(egui::Id::new("switcher")).show(ctx, |ui| {
+            ui.set_width(320.0);
+            ui.add(egui::TextEdit::singleline(&mut switcher.query).hint_text("Switch output to…"))
+                .request_focus();
+            ui.add_space(4.0);
+
+            if sinks.is_empty() {
+                ui.label(egui::RichText::new("No matching outputs").weak());
+            }
+            for (index, (id, description, is_default)) in sinks.iter().enumerate() {
+                let mut text = egui::RichText::new(description);
+                if *is_default {
+                    text = text.color(self.accent);
+                }
+                if ui.selectable_label(index == switcher.highlighted, text).clicked() {
+                    chosen = Some(*id);
+                }
+            }
+        });
+
+        if let Some(id) = chosen {
+            self.send_change(PwCommand::SetDefault(id));
+            self.switcher = None;
+        } else if enter || modal.should_close() {
+            self.switcher = None;
+        }
+    }
+
+    fn render_confirm(&mut self, ctx: &egui::Context) {
+        let Some(confirm) = &self.confirm else { return };
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let modal = egui::Modal::new(egui::Id::new("confirm")).show(ctx, |ui| {
+            ui.set_width(300.0);
+            ui.label(egui::RichText::new(confirm.title).strong());
+            ui.label(&confirm.message);
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                confirmed = ui.button(confirm.action).clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+
+        if confirmed {
+            if let Some(confirm) = self.confirm.take() {
+                self.send_change(confirm.command);
+            }
+        } else if cancelled || modal.should_close() {
+            self.confirm = None;
+        }
+    }
+
+    /// Pick two nodes and link them, all channels at once.
+    fn render_connect(&mut self, ui: &mut egui::Ui, state: &AppState) {
+        // Nodes that have ports in the given direction, by id.
+        let with_ports = |output: bool| {
+            let mut nodes: Vec<&AudioNode> = state
+                .nodes
+                .values()
+                .filter(|n| state.ports.values().any(|p| p.node_id == n.id && p.is_output == output))
+                .collect();
+            nodes.sort_by_key(|n| n.id);
+            nodes
+        };
+        let description = |id: Option<u32>| {
+            id.and_then(|id| state.nodes.get(&id)).map_or("Pick…", |n| n.description.as_str())
+        };
+
+        ui.add_enabled_ui(!self.settings.locked, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("link_source")
+                    .selected_text(description(self.link_source))
+                    .show_ui(ui, |ui| {
+                        for node in with_ports(true) {
+                            ui.selectable_value(&mut self.link_source, Some(node.id), &node.description);
+                        }
+                    });
+                ui.label("→");
+                egui::ComboBox::from_id_salt("link_target")
+                    .selected_text(description(self.link_target))
+                    .show_ui(ui, |ui| {
+                        for node in with_ports(false) {
+                            ui.selectable_value(&mut self.link_target, Some(node.id), &node.description);
+                        }
+                    });
+
+                let known = |id: &u32| state.nodes.contains_key(id);
+                let pair = self
+                    .link_source
+                    .zip(self.link_target)
+                    .filter(|(source, target)| source != target && known(source) && known(target));
+                if ui.add_enabled(pair.is_some(), egui::Button::new("Connect")).clicked()
+                    && let Some((source, target)) = pair
+                {
+                    self.send_change(PwCommand::CreateLink(source, target));
+                }
+            });
+        });
+    }
+
+    /// The role dropdown above the playback streams, offered once any stream has a role.
+    /// The mute-all button then acts on the filtered streams, e.g. every "Event" sound.
+    fn render_role_filter(&mut self, ui: &mut egui::Ui, streams: &[&AudioNode]) {
+        let roles: BTreeSet<&str> = streams.iter().filter_map(|n| n.role.as_deref()).collect();
+        if roles.is_empty() && self.role_filter == RoleFilter::All {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Role:");
+            egui::ComboBox::from_id_salt("role_filter")
+                .selected_text(self.role_filter.label())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.role_filter, RoleFilter::All, RoleFilter::All.label());
+                    for role in &roles {
+                        for filter in [RoleFilter::Only(role.to_string()), RoleFilter::Hide(role.to_string())] {
+                            let label = filter.label();
+                            ui.selectable_value(&mut self.role_filter, filter, label);
+                        }
+                    }
+                });
+        });
+    }
+
+    /// Every link between audio nodes, grouped by the node it comes from.
+    fn render_links(&self, ui: &mut egui::Ui, state: &AppState) {
+        let node_name = |id: u32| {
+            state
+                .nodes
+                .get(&id)
+                .map_or_else(|| format!("Node {}", id), |n| n.description.clone())
+        };
+        let port_name = |id: u32| state.ports.get(&id).map_or("?", |p| p.name.as_str());
+
+        // Links between non-audio nodes (MIDI, video) aren't shown.
+        let mut by_output: BTreeMap<u32, Vec<(u32, &LinkInfo)>> = BTreeMap::new();
+        for (&id, link) in &state.links {
+            if state.nodes.contains_key(&link.output_node) || state.nodes.contains_key(&link.input_node) {
+                by_output.entry(link.output_node).or_default().push((id, link));
+            }
+        }
+
+        if by_output.is_empty() {
+            ui.label("No links between audio nodes");
+            return;
+        }
+
+        for (output_node, mut links) in by_output {
+            links.sort_by_key(|(_, l)| (l.input_node, l.output_port, l.input_port));
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                ui.add(egui::Label::new(egui::RichText::new(node_name(output_node)).strong()).truncate());
+                for (id, link) in links {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} → {}: {}",
+                            port_name(link.output_port),
+                            node_name(link.input_node),
+                            port_name(link.input_port)
+                        ));
+                        if ui
+                            .add_enabled(!self.settings.locked, egui::Button::new("✖").small())
+                            .on_hover_text("Disconnect")
+                            .clicked()
+                        {
+                            self.send_change(PwCommand::DestroyLink(id));
+                        }
+                        match &link.state {
+                            LinkState::Active => {}
+                            LinkState::Paused => {
+                                ui.weak("paused");
+                            }
+                            LinkState::Connecting => {
+                                ui.weak("connecting");
+                            }
+                            LinkState::Error(message) => {
+                                ui.colored_label(ui.visuals().error_fg_color, "error").on_hover_text(message);
+                            }
+                        }
+                    });
+                }
+            });
+        }
+    }
+
+    fn render_card(&mut self, ui: &mut egui::Ui, card: &Card, state: &AppState) {
         egui::Frame::group(ui.style()).show(ui, |ui| {
             ui.set_min_width(ui.available_width());
             ui.vertical(|ui| {
@@ -232,7 +1941,7 @@ impl CopperApp {
                     ui.label(egui::RichText::new(&card.description).strong());
                 });
 
-                ui.horizontal(|ui| {
+                ui.add_enabled_ui(!self.settings.locked, |ui| ui.horizontal(|ui| {
                     ui.label("Profile:");
                     let current_profile_name = card
                         .active_profile_index
@@ -240,11 +1949,24 @@ impl CopperApp {
                         .map(|p| p.description.clone())
                         .unwrap_or_else(|| "Unknown".to_string());
 
-                    egui::ComboBox::from_id_salt(card.id)
+                    let combo = egui::ComboBox::from_id_salt(card.id)
                         .selected_text(current_profile_name)
+                        .height(320.0)
                         .show_ui(ui, |ui| {
+                            if self.profile_filter.0 != card.id {
+                                self.profile_filter = (card.id, String::new());
+                            }
+                            if card.profiles.len() > PROFILE_FILTER_MIN {
+                                ui.add(egui::TextEdit::singleline(&mut self.profile_filter.1).hint_text("Filter profiles"))
+                                    .request_focus();
+                            }
+                            let filter = self.profile_filter.1.to_lowercase();
+
                             for profile in &card.profiles {
-                                if state.hide_unavailable_profiles && !profile.available {
+                                if self.settings.hide_unavailable_profiles && !profile.available {
+                                    continue;
+                                }
+                                if !filter.is_empty() && !profile.description.to_lowercase().contains(&filter) {
                                     continue;
                                 }
 
@@ -255,14 +1977,364 @@ impl CopperApp {
 
                                 let is_selected = card.active_profile_index == Some(profile.index);
                                 if ui.selectable_label(is_selected, label).clicked() {
-                                    let _ = self.tx.send(PwCommand::SetCardProfile(card.id, profile.index));
+                                    self.request_profile(card, profile.index, state);
                                 }
                             }
                         });
+
+                    if combo.inner.is_none() && self.profile_filter.0 == card.id {
+                        self.profile_filter.1.clear();
+                    }
+                }));
+
+                let mut card_nodes: Vec<&AudioNode> =
+                    state.nodes.values().filter(|n| !n.is_stream && n.device_id == Some(card.id)).collect();
+                card_nodes.sort_by(|a, b| (!a.is_sink, &a.description).cmp(&(!b.is_sink, &b.description)));
+                for node in card_nodes {
+                    self.render_port(ui, card, node);
+                }
+
+                if let Some(recommended) = card.recommended_profile() {
+                    let is_active = card.active_profile_index == Some(recommended.index);
+                    let button = ui
+                        .add_enabled(!is_active && !self.settings.locked, egui::Button::new("Reset to recommended profile").small())
+                        .on_hover_text(format!("Switch to {}", recommended.description));
+                    if button.clicked() {
+                        self.request_profile(card, recommended.index, state);
+                    }
+                }
+
+                let reset = ui
+                    .add_enabled(!self.settings.locked, egui::Button::new("Reset port volumes").small())
+                    .on_hover_text("Set the active ports back to the session manager's default volume");
+                if reset.clicked() {
+                    self.confirm = Some(Confirm {
+                        title: "Reset port volumes?",
+                        message: format!(
+                            "The active outputs of {} will be set to 40% and its inputs to 100%, the session \
+                             manager's defaults, and saved in place of the current volumes. Mute is left as it is.",
+                            card.description
+                        ),
+                        action: "Reset",
+                        command: PwCommand::ResetRouteVolumes(card.id),
+                    });
+                }
+            });
+        });
+    }
+
+    /// Save the volumes of everything playing now under a name, and bring them back later.
+    fn render_presets(&mut self, ui: &mut egui::Ui, state: &AppState) {
+        ui.label(egui::RichText::new("Presets").strong().color(self.accent));
+
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_min_width(ui.available_width());
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("preset")
+                    .selected_text(if self.presets.is_empty() { "No presets" } else { "Pick…" })
+                    .show_ui(ui, |ui| {
+                        for name in self.presets.keys() {
+                            if ui.selectable_label(*name == self.preset_name, name).clicked() {
+                                self.preset_name = name.clone();
+                            }
+                        }
+                    });
+                ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("Preset name").desired_width(120.0));
+            });
+
+            let name = self.preset_name.trim().to_string();
+            let exists = self.presets.contains_key(&name);
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+                    .on_hover_text("Store the current volume and mute of every device and stream")
+                    .clicked()
+                {
+                    let preset = state.nodes.values().map(|n| (n.stable_id.clone(), (n.volume, n.muted))).collect();
+                    self.presets.insert(name.clone(), preset);
+                    config::save_presets(&self.presets);
+                }
+                ui.add_enabled_ui(!self.settings.locked, |ui| {
+                    if ui
+                        .add_enabled(exists, egui::Button::new("Apply"))
+                        .on_hover_text("Streams that aren't open yet get their volume when they appear")
+                        .clicked()
+                    {
+                        self.apply_preset(&name, state);
+                    }
+                });
+                if ui.add_enabled(exists, egui::Button::new("Delete")).clicked() {
+                    self.presets.remove(&name);
+                    config::save_presets(&self.presets);
+                }
+            });
+        });
+    }
+
+    fn apply_preset(&mut self, name: &str, state: &AppState) {
+        let Some(preset) = self.presets.get(name).cloned() else { return };
+        self.pending_preset.clear();
+        for (stable_id, (volume, muted)) in preset {
+            let mut found = false;
+            for node in state.nodes.values().filter(|n| n.stable_id == stable_id) {
+                self.apply_preset_entry(node, volume, muted);
+                found = true;
+            }
+            if !found {
+                self.pending_preset.insert(stable_id, (volume, muted));
+            }
+        }
+    }
+
+    /// Give nodes that appeared since a preset was applied their volume from it.
+    fn apply_pending_preset(&mut self) {
+        if self.pending_preset.is_empty() || self.settings.locked {
+            return;
+        }
+        let state = self.state.lock();
+        let mut applied = Vec::new();
+        for node in state.nodes.values() {
+            if let Some(&(volume, muted)) = self.pending_preset.get(&node.stable_id) {
+                self.apply_preset_entry(node, volume, muted);
+                applied.push(node.stable_id.clone());
+            }
+        }
+        drop(state);
+        for stable_id in applied {
+            self.pending_preset.remove(&stable_id);
+        }
+    }
+
+    fn apply_preset_entry(&self, node: &AudioNode, volume: f32, muted: bool) {
+        let volume = volume.clamp(0.0, self.max_volume());
+        if (node.volume - volume).abs() > 0.005 {
+            self.send_change(PwCommand::SetVolume(node.id, volume));
+        }
+        if node.muted != muted {
+            self.send_change(PwCommand::SetMute(node.id, muted));
+        }
+    }
+
+    /// Graph-wide sample rate and quantum overrides, for when a device or app needs a fixed clock.
+    fn render_clock(&mut self, ui: &mut egui::Ui, state: &AppState) {
+        egui::CollapsingHeader::new("Advanced").show(ui, |ui| {
+            ui.add_enabled_ui(!self.settings.locked, |ui| {
+                egui::Grid::new("clock").num_columns(2).show(ui, |ui| {
+                    ui.label("Sample rate:");
+                    if let Some(rate) = clock_combo(ui, "forced_rate", state.forced_rate, &FORCED_RATES, " Hz") {
+                        self.send_change(PwCommand::SetForcedRate(rate));
+                    }
+                    ui.end_row();
+
+                    ui.label("Quantum:");
+                    if let Some(quantum) = clock_combo(ui, "forced_quantum", state.forced_quantum, &FORCED_QUANTA, " samples") {
+                        self.send_change(PwCommand::SetForcedQuantum(quantum));
+                    }
+                    ui.end_row();
                 });
             });
+            ui.label(egui::RichText::new("Applies to the whole graph until PipeWire restarts.").weak().small());
         });
     }
+
+    /// A port picker for one of the card's devices, shown when it has more than one port.
+    fn render_port(&mut self, ui: &mut egui::Ui, card: &Card, node: &AudioNode) {
+        let ports = card.ports_for(node);
+        if ports.len() < 2 {
+            return;
+        }
+
+        ui.add_enabled_ui(!self.settings.locked, |ui| ui.horizontal(|ui| {
+            ui.label(format!("{}:", node.description));
+            let current = ports
+                .iter()
+                .find(|r| node.route_index == Some(r.index))
+                .map(|r| if r.available { r.description.clone() } else { format!("{} (unplugged)", r.description) })
+                .unwrap_or_else(|| "Unknown".to_string());
+
+            egui::ComboBox::from_id_salt(("port", node.id))
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for port in &ports {
+                        if self.settings.hide_unavailable_profiles && !port.available {
+                            continue;
+                        }
+
+                        let mut label = port.description.clone();
+                        if !port.available {
+                            label.push_str(" (unplugged)");
+                        }
+
+                        let is_selected = node.route_index == Some(port.index);
+                        if ui.selectable_label(is_selected, label).clicked() && !is_selected {
+                            self.send_change(PwCommand::SetPort(node.id, port.index));
+                        }
+                    }
+                });
+        }));
+    }
+}
+
+/// A small dot for the node's state: green while running, red on error, grey otherwise.
+fn state_dot(ui: &mut egui::Ui, state: NodeState) {
+    let (color, text) = match state {
+        NodeState::Running => (egui::Color32::from_rgb(80, 200, 100), "Running"),
+        NodeState::Error => (ui.visuals().error_fg_color, "Error"),
+        NodeState::Idle => (ui.visuals().weak_text_color(), "Idle"),
+        NodeState::Suspended => (ui.visuals().weak_text_color().gamma_multiply(0.5), "Suspended"),
+    };
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
+    ui.painter().circle_filled(rect.center(), 3.5, color);
+    response.on_hover_text(text);
 }
 
+/// Sample rates offered for forcing the graph clock.
+const FORCED_RATES: [u32; 5] = [44100, 48000, 88200, 96000, 192000];
+/// Quantum sizes offered for forcing the graph clock, in samples.
+const FORCED_QUANTA: [u32; 8] = [32, 64, 128, 256, 512, 1024, 2048, 4096];
 
+/// A combo of "Off" plus `choices`; returns the picked value, 0 for "Off".
+fn clock_combo(ui: &mut egui::Ui, id: &str, current: Option<u32>, choices: &[u32], unit: &str) -> Option<u32> {
+    let mut picked = None;
+    let selected = current.map_or_else(|| "Off".to_string(), |v| format!("{}{}", v, unit));
+    egui::ComboBox::from_id_salt(id).selected_text(selected).show_ui(ui, |ui| {
+        if ui.selectable_label(current.is_none(), "Off").clicked() && current.is_some() {
+            picked = Some(0);
+        }
+        // A value forced outside Copper still shows up as the selection.
+        if let Some(value) = current.filter(|v| !choices.contains(v)) {
+            let _ = ui.selectable_label(true, format!("{}{}", value, unit));
+        }
+        for &value in choices {
+            let is_selected = current == Some(value);
+            if ui.selectable_label(is_selected, format!("{}{}", value, unit)).clicked() && !is_selected {
+                picked = Some(value);
+            }
+        }
+    });
+    picked
+}
+
+/// Devices a stream can be moved to: outputs for playback; inputs, then output
+/// monitors, for recording.
+fn stream_targets<'a>(stream: &AudioNode, state: &'a AppState) -> Vec<&'a AudioNode> {
+    let mut targets: Vec<&AudioNode> = state
+        .nodes
+        .values()
+        .filter(|n| !n.is_stream && (n.is_sink || !stream.is_sink))
+        .collect();
+    targets.sort_by(|a, b| (a.is_sink, &a.description).cmp(&(b.is_sink, &b.description)));
+    targets
+}
+
+fn stream_target_text(stream: &AudioNode, target: &AudioNode) -> String {
+    if !stream.is_sink && target.is_sink {
+        format!("Monitor of {}", target.description)
+    } else {
+        target.description.clone()
+    }
+}
+
+fn node_icon(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::Speakers => "🔊",
+        NodeKind::Headphones => "🎧",
+        NodeKind::Display => "📺",
+        NodeKind::Microphone => "🎤",
+        NodeKind::Browser => "🌐",
+        NodeKind::Music => "🎵",
+        NodeKind::Video => "🎬",
+        NodeKind::Game => "🎮",
+        NodeKind::Call => "📞",
+        NodeKind::Notification => "🔔",
+        NodeKind::App => "🗔",
+    }
+}
+
+/// A name for channel `index` of a node with `count` channels, guessing the
+/// usual layout for common counts.
+fn channel_label(count: usize, index: usize) -> String {
+    const SURROUND: [&str; 6] = ["FL", "FR", "FC", "LFE", "RL", "RR"];
+    match count {
+        1 => "Mono".to_string(),
+        2 => ["Left", "Right"][index].to_string(),
+        6 => SURROUND[index].to_string(),
+        _ => format!("Ch {}", index + 1),
+    }
+}
+
+/// A short note when a device's configured default and active default disagree,
+/// e.g. the configured device was unplugged and PipeWire fell back to another one.
+fn configured_default_note(node: &AudioNode, state: &AppState) -> Option<(&'static str, String)> {
+    if node.is_stream {
+        return None;
+    }
+
+    let (active, configured) = if node.is_sink {
+        (state.default_sink_name.as_ref(), state.configured_sink_name.as_ref())
+    } else {
+        (state.default_source_name.as_ref(), state.configured_source_name.as_ref())
+    };
+    let configured = configured?;
+    if active == Some(configured) {
+        return None;
+    }
+
+    if node.is_default {
+        let configured_desc = state
+            .find_node_by_name(configured)
+            .map_or(configured.as_str(), |n| n.description.as_str());
+        Some(("fallback", format!("Configured default \"{}\" is not active; using this device instead", configured_desc)))
+    } else if node.name == *configured {
+        Some(("configured default", "This is the configured default, but another device is currently active".to_string()))
+    } else {
+        None
+    }
+}
+
+/// Developer-mode error counter, highlighted for a moment after it increases.
+fn render_error_stats(ui: &mut egui::Ui, node: &AudioNode) {
+    let recent = node.error_at.is_some_and(|at| at.elapsed() < ERROR_HIGHLIGHT);
+    let mut text = egui::RichText::new(format!("errors: {}", node.error_count)).small();
+    text = if recent || node.error.is_some() {
+        text.color(ui.visuals().warn_fg_color)
+    } else {
+        text.weak()
+    };
+
+    let label = ui.label(text);
+    if let Some(error) = &node.error {
+        label.on_hover_text(error);
+    }
+    if recent {
+        ui.ctx().request_repaint_after(Duration::from_millis(250));
+    }
+}
+
+/// "🔗 linked" on a device whose volume moves with others, naming them on hover.
+fn render_link_badge(ui: &mut egui::Ui, node: &AudioNode, state: &AppState) {
+    let linked = state.linked_nodes(node);
+    if linked.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = linked.iter().map(|n| n.description.as_str()).collect();
+    ui.label(egui::RichText::new("🔗 linked").small().weak())
+        .on_hover_text(format!("Volume moves together with {}", names.join(", ")));
+}
+
+/// Group streams by application, keeping the order of each app's first stream.
+/// Streams without an application name stay on their own.
+fn group_by_app(streams: Vec<&AudioNode>) -> Vec<Vec<&AudioNode>> {
+    let mut groups: Vec<Vec<&AudioNode>> = Vec::new();
+    for node in streams {
+        let existing = node
+            .app_name
+            .as_ref()
+            .and_then(|app| groups.iter_mut().find(|g| g[0].app_name.as_ref() == Some(app)));
+        match existing {
+            Some(group) => group.push(node),
+            None => groups.push(vec![node]),
+        }
+    }
+    groups
+}